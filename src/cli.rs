@@ -3,42 +3,98 @@ extern crate stopwatch;
 
 use std::env;
 use std::fs;
+use std::rc::Rc;
 
 use stopwatch::Stopwatch;
 
 use nprs::image;
-use nprs::extract::cser::feature::{AspectRatio, Compactness, NumHoles, HorizontalCrossings};
-use nprs::extract::cser::{FullTrace, EmptyTrace};
-use nprs::extract::cser::{Region, TracedRegion, CserDetector};
+use nprs::extract::cser::feature::{FeatureSet, DEFAULT_LAYOUT, parse_layout};
+use nprs::extract::cser::FullTrace;
+use nprs::extract::cser::{Region, RegionParams, TracedRegion, CserDetector};
 use nprs::extract::RegionDetector;
+use nprs::ml::{Model, explain};
 
-type Features = (AspectRatio, Compactness, HorizontalCrossings, NumHoles);
+type Features = FeatureSet;
 type Reg = Region<Features>;
 type Detector<'a> = CserDetector<TracedRegion<Reg>, FullTrace<'a, Reg>>;
 
+static USAGE: &'static str = "usage: nprs-cli <file name> [--features <features>] [--model <model file>]";
+
+struct Args {
+    file_name: String,
+    features: Option<String>,
+    model: Option<String>
+}
+
+fn parse_args() -> Args {
+    let mut args = env::args().skip(1);
+    let file_name = args.next().expect(USAGE);
+    let mut res = Args { file_name: file_name, features: None, model: None };
+
+    while let Some(flag) = args.next() {
+        let value = Some(args.next().expect(USAGE));
+        match &flag[..] {
+            "--features" => res.features = value,
+            "--model" => res.model = value,
+            _ => panic!("{}", USAGE)
+        }
+    }
+
+    res
+}
+
 fn main() {
-    assert!(env::args().count() == 2, "usage: nprs-cli <file name>");
+    let args = parse_args();
+
+    let model = args.model.as_ref().map(|path| {
+        Model::load(path).unwrap_or_else(|e| panic!("Failed to load model `{}`: {:?}", path, e))
+    });
 
-    if let Some(file_name) = env::args().nth(1) {
-        let img = image::io::load_from_file(&file_name).unwrap();
+    // model knows which features it was trained on
+    let features = args.features.clone()
+        .or(model.as_ref().map(|m| m.features.clone()))
+        .unwrap_or(DEFAULT_LAYOUT.to_string());
+    let layout = parse_layout(&features)
+        .unwrap_or_else(|e| panic!("Invalid feature list `{}`: {:?}", features, e));
 
-        let sw = Stopwatch::start_new();
+    let img = image::io::load_from_file(&args.file_name).unwrap();
+
+    let sw = Stopwatch::start_new();
+    let schema = FeatureSet::schema(&layout);
+    let params = RegionParams::new(layout);
+    let detect = |params: &RegionParams<_>| {
         let mut full_trace: FullTrace<Reg> = FullTrace::new(
             "trace",
+            schema.clone(),
             img.width(), img.height(),
             (5, 5),
             (300, 300),
         );
-        let mut empty_trace = EmptyTrace;
+        Detector::detect(&img, params, &mut full_trace);
+        full_trace
+    };
 
-        Detector::detect(&img, &mut full_trace);
+    let full_trace = match model {
+        Some(m) => {
+            if m.second_stage.len() > 0 {
+                panic!("Model with second-stage features `{}` verifies candidates, it can't weight regions", m.second_stage);
+            }
+            m.check_schema(&schema)
+                .unwrap_or_else(|e| panic!("Model doesn't match features `{}`: {:?}", features, e));
+            let m = Rc::new(m);
+            let mut full_trace = detect(&params.clone().weighted_by(m.clone()));
+            // tells why regions got their weights
+            full_trace.explain(|x| explain(&*m, x));
+            full_trace
+        },
+        None => detect(&params)
+    };
 
-        let mut f = fs::File::create("trace.json")
-            .unwrap_or_else(|e| panic!("Failed to create trace.json file: {:?}", e));
+    let mut f = fs::File::create("trace.json")
+        .unwrap_or_else(|e| panic!("Failed to create trace.json file: {:?}", e));
 
-        full_trace.write_zipped_json(&mut f)
-            .unwrap_or_else(|e| panic!("Failed to write trace as zipped json: {:?}", e));
+    full_trace.write_zipped_json(&mut f)
+        .unwrap_or_else(|e| panic!("Failed to write trace as zipped json: {:?}", e));
 
-        println!("region detection took {}ms", sw.elapsed_ms());
-    }
+    println!("region detection took {}ms", sw.elapsed_ms());
 }
@@ -21,8 +21,9 @@ impl<A: Incremental + ExtremalRegion + Sized, B: Trace<A>> CserDetector<A, B> {
 impl<A: Incremental + ExtremalRegion + Sized, B: Trace<A>> RegionDetector for CserDetector<A, B> {
     type Region = A;
     type Trace = B;
+    type Params = <A as Incremental>::Params;
 
-    fn detect(image: &Image<u8>, trace: &mut B) -> Vec<A> {
+    fn detect(image: &Image<u8>, params: &<A as Incremental>::Params, trace: &mut B) -> Vec<A> {
         let baskets = hist(image);
         let mut all_regions: Vec<A> = vec![];
         let mut reg_image: Image<Option<usize>> = image.map( |_| None );
@@ -37,7 +38,8 @@ impl<A: Incremental + ExtremalRegion + Sized, B: Trace<A>> RegionDetector for Cs
                     i as i32,
                     image, &mut reg_image,
                     &mut all_regions,
-                    &mut neighbors_buf
+                    &mut neighbors_buf,
+                    params
                 );
             }
             trace.step(i as i32, &all_regions, &reg_image);
@@ -55,14 +57,15 @@ pub fn process_point<A: Incremental + ExtremalRegion + Sized>(
     img: &Image<u8>,
     reg_image: &mut Image<Option<usize>>,
     all_regions: &mut Vec<A>,
-    neighbors_buf: &mut Vec<usize>
+    neighbors_buf: &mut Vec<usize>,
+    params: &<A as Incremental>::Params
 ) {
     find_neighbors(&reg_image, p.clone(), neighbors_buf);
 
     match &mut neighbors_buf[..] {
         [] => {
             let idx = all_regions.len();
-            all_regions.push(A::init(p, idx, thres));
+            all_regions.push(A::init(p, idx, thres, params));
             reg_image.set_pixel(p.x, p.y, Some(idx));
         },
         [r_idx] => {
@@ -78,8 +81,9 @@ pub fn process_point<A: Incremental + ExtremalRegion + Sized>(
             all.reverse();
             match all {
                 [r1_idx, rest..] => {
-                    all_regions[r1_idx].increment(p, thres, img, reg_image);
-                    reg_image.set_pixel(p.x, p.y, Some(r1_idx));
+                    // regions are merged before the point is added, so that
+                    // incremental features see all of its neighbors as
+                    // belonging to the same region
                     for r_idx in rest {
                         if let Some((r1, r2)) = index_twice(&mut all_regions[..], r1_idx, *r_idx) {
                             r1.merge(r2, thres, img, reg_image);
@@ -90,6 +94,8 @@ pub fn process_point<A: Incremental + ExtremalRegion + Sized>(
                             panic!("failed to index regions");
                         }
                     }
+                    all_regions[r1_idx].increment(p, thres, img, reg_image);
+                    reg_image.set_pixel(p.x, p.y, Some(r1_idx));
                 },
                 _ => panic!("can't happen")
             }
@@ -1,9 +1,22 @@
 pub use super::detector::*;
 pub use image::Image;
 pub use structures::{Point, Rect};
-pub use extract::ExtremalRegion;
-pub use extract::cser::{Incremental, Region};
-pub use extract::cser::feature::AspectRatio;
+pub use extract::{ExtremalRegion, RegionDetector};
+pub use extract::cser::{Incremental, Region, RegionParams, CserDetector, VerifyTrace};
+pub use extract::cser::feature::{AspectRatio, Compactness, HorizontalCrossings, NumHoles, AreaStability, Orientation};
+
+pub type AllFeatures = (AspectRatio, Compactness, HorizontalCrossings, NumHoles, AreaStability, Orientation);
+
+/// Image with pseudo-random pixels in `0..levels` range, scaled to `0..255`.
+pub fn random_image(seed: u32, width: usize, height: usize, levels: u32) -> Image<u8> {
+    let mut s = seed;
+    let mut data = vec![];
+    for _ in 0..(width * height) {
+        s = s.wrapping_mul(1103515245).wrapping_add(12345);
+        data.push((((s >> 16) % levels) * (255 / (levels - 1))) as u8);
+    }
+    Image::from_data(data, width, height)
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TestInc {
@@ -12,7 +25,9 @@ pub struct TestInc {
 }
 
 impl Incremental for TestInc {
-    fn init(p: Point, _: usize, _: i32) -> Self {
+    type Params = ();
+
+    fn init(p: Point, _: usize, _: i32, _: &()) -> Self {
         TestInc { points: vec![p], peaks: vec![] }
     }
 
@@ -152,7 +167,7 @@ describe! detect_regions {
                 peaks: vec![]
             };
 
-            process_point(new_point, 0, &img, &mut reg_img, &mut regions, &mut neighbors_buf);
+            process_point(new_point, 0, &img, &mut reg_img, &mut regions, &mut neighbors_buf, &());
             assert_eq!(*regions.last().unwrap(), expected_region);
         }
 
@@ -167,7 +182,7 @@ describe! detect_regions {
                 .map(|x| if x.clone() != 0u8 { Some((x - 1) as usize) } else { None })
                 .collect();
 
-            process_point(Point { x: 5, y: 0 }, 0, &img, &mut reg_img, &mut regions, &mut neighbors_buf);
+            process_point(Point { x: 5, y: 0 }, 0, &img, &mut reg_img, &mut regions, &mut neighbors_buf, &());
 
             assert_eq!(reg_img.data(), &expected_data[..]);
         }
@@ -183,7 +198,7 @@ describe! detect_regions {
                 .map(|x| if x.clone() != 0u8 { Some((x - 1) as usize) } else { None })
                 .collect();
 
-            process_point(Point { x: 0, y: 0 }, 0, &img, &mut reg_img, &mut regions, &mut neighbors_buf);
+            process_point(Point { x: 0, y: 0 }, 0, &img, &mut reg_img, &mut regions, &mut neighbors_buf, &());
 
             assert_eq!(reg_img.data(), &expected_data[..]);
             assert_eq!(regions[0].points().len(), 4);
@@ -200,7 +215,7 @@ describe! detect_regions {
                 .map(|x| if x.clone() != 0u8 { Some((x - 1) as usize) } else { None })
                 .collect();
 
-            process_point(Point { x: 1, y: 1 }, 0, &img, &mut reg_img, &mut regions, &mut neighbors_buf);
+            process_point(Point { x: 1, y: 1 }, 0, &img, &mut reg_img, &mut regions, &mut neighbors_buf, &());
 
             assert_eq!(reg_img.data(), &expected_data[..]);
         }
@@ -216,12 +231,48 @@ describe! detect_regions {
                 .map(|x| if x.clone() != 0u8 { Some((x - 1) as usize) } else { None })
                 .collect();
 
-            process_point(Point { x: 2, y: 2 }, 0, &img, &mut reg_img, &mut regions, &mut neighbors_buf);
+            process_point(Point { x: 2, y: 2 }, 0, &img, &mut reg_img, &mut regions, &mut neighbors_buf, &());
 
             assert_eq!(reg_img.data(), &expected_data[..]);
         }
     }
 
+    describe! merge_order {
+        it "should merge regions before adding the point that joins them" {
+            let img: Image<u8> = Image::from_data(vec![0, 1, 0], 3, 1);
+            let mut reg_img: Image<Option<usize>> = Image::from_data(vec![None; 3], 3, 1);
+            let mut regions: Vec<Region<Compactness>> = vec![];
+            let mut neighbors_buf: Vec<usize> = vec![];
+            let params = RegionParams::new(());
+
+            process_point(Point { x: 0, y: 0 }, 0, &img, &mut reg_img, &mut regions, &mut neighbors_buf, &params);
+            process_point(Point { x: 2, y: 0 }, 0, &img, &mut reg_img, &mut regions, &mut neighbors_buf, &params);
+            process_point(Point { x: 1, y: 0 }, 1, &img, &mut reg_img, &mut regions, &mut neighbors_buf, &params);
+
+            // the joining point sees both neighbors as the merged region,
+            // so the perimeter of the 3x1 line is 8 rather than 10
+            let idx = reg_img[(1, 0)].unwrap();
+            let mut v = vec![];
+            regions[idx].feature_vec(&mut v);
+            assert_eq!(v, vec![8.0f32 / 3.0f32]);
+        }
+    }
+
+    describe! consistency {
+        it "should keep incremental features equal to batch computation" {
+            for seed in 0..10 {
+                for levels in [2, 4, 16, 256].iter() {
+                    let img = random_image(seed, 24, 16, *levels);
+                    let mut trace = VerifyTrace::new(&img, ());
+
+                    CserDetector::<Region<AllFeatures>, VerifyTrace<()>>::detect(&img, &RegionParams::new(()), &mut trace);
+
+                    assert_eq!(trace.mismatches(), &[][..]);
+                }
+            }
+        }
+    }
+
     describe! index_twice {
         it "should return references to indexed elements" {
             let mut sl = [1, 2, 3, 4];
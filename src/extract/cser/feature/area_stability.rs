@@ -0,0 +1,229 @@
+use std::cmp::max;
+
+use image::Image;
+use structures::Point;
+use extract::cser::{Feature, Incremental};
+use extract::cser::feature::Batch;
+use extract::cser::feature::schema::{Column, Columns};
+
+/// Number of gray levels over which area variation is measured, unless
+/// configured otherwise.
+pub static DEFAULT_DELTA: i32 = 5;
+
+/// MSER-like stability of a region: relative area growth over the last
+/// `delta` gray levels, `(|R(t)| - |R(t - delta)|) / |R(t)|`.
+///
+/// Area at threshold `t` is the number of region points with intensity `<= t`,
+/// so the area history of merged regions is just the sum of their histories.
+/// Growth older than `delta` levels is collapsed into `settled`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AreaStability {
+    delta: i32,
+    settled: i32,
+    growth: Vec<(i32, i32)>,
+    thres: i32
+}
+
+impl AreaStability {
+    /// Stability of a new region measured over `delta` gray levels.
+    pub fn with_delta(delta: i32, thres: i32) -> AreaStability {
+        AreaStability {
+            delta: delta,
+            settled: 0,
+            growth: vec![(thres, 1)],
+            thres: thres
+        }
+    }
+
+    /// Reference computation of the stability over `delta` gray levels,
+    /// see `Batch`.
+    pub fn batch_with_delta(delta: i32, points: &[Point], thres: i32, img: &Image<u8>) -> AreaStability {
+        let mut hist = [0; 256];
+        for p in points {
+            hist[img[(p.x, p.y)] as usize] += 1;
+        }
+
+        let mut a = AreaStability {
+            delta: delta,
+            settled: 0,
+            growth: vec![],
+            thres: thres
+        };
+
+        for t in 0..256 {
+            if hist[t] > 0 {
+                a.record(t as i32, hist[t]);
+            }
+        }
+
+        a.settle(thres);
+        a
+    }
+
+    pub fn column(delta: i32) -> Column {
+        Column::new(&format!("area_variation_{}", delta), Some(0.0f32), Some(1.0f32))
+    }
+
+    pub fn delta(&self) -> i32 {
+        self.delta
+    }
+
+    pub fn area(&self) -> i32 {
+        self.settled + self.growth_area()
+    }
+
+    pub fn variation(&self) -> f32 {
+        (self.growth_area() as f32) / (self.area() as f32)
+    }
+
+    fn growth_area(&self) -> i32 {
+        self.growth.iter().fold(0, |a, g| a + g.1)
+    }
+
+    fn record(&mut self, thres: i32, area: i32) {
+        if let Some(last) = self.growth.last_mut() {
+            if last.0 == thres {
+                last.1 += area;
+                return;
+            }
+        }
+        self.growth.push((thres, area));
+    }
+
+    fn settle(&mut self, thres: i32) {
+        self.thres = max(self.thres, thres);
+        let limit = self.thres - self.delta;
+        let n = self.growth.iter().take_while(|g| g.0 <= limit).count();
+        for g in self.growth.drain(..n) {
+            self.settled += g.1;
+        }
+    }
+}
+
+impl Incremental for AreaStability {
+    type Params = ();
+
+    fn init(_: Point, _: usize, thres: i32, _: &()) -> Self {
+        AreaStability::with_delta(DEFAULT_DELTA, thres)
+    }
+
+    fn increment(&mut self, _: Point, thres: i32, _: &Image<u8>, _: &Image<Option<usize>>) {
+        self.record(thres, 1);
+        self.settle(thres);
+    }
+
+    fn merge(&mut self, other: &Self, thres: i32, _: &Image<u8>, _: &Image<Option<usize>>) {
+        debug_assert_eq!(self.delta, other.delta);
+
+        let mut merged = Vec::with_capacity(self.growth.len() + other.growth.len());
+        {
+            let (mut i, mut j) = (0, 0);
+            let (a, b) = (&self.growth, &other.growth);
+            while i < a.len() || j < b.len() {
+                if j == b.len() || (i < a.len() && a[i].0 < b[j].0) {
+                    merged.push(a[i]);
+                    i += 1;
+                } else if i == a.len() || b[j].0 < a[i].0 {
+                    merged.push(b[j]);
+                    j += 1;
+                } else {
+                    merged.push((a[i].0, a[i].1 + b[j].1));
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+
+        self.growth = merged;
+        self.settled += other.settled;
+        self.settle(thres);
+    }
+}
+
+impl Batch for AreaStability {
+    fn batch(points: &[Point], _: usize, thres: i32, img: &Image<u8>, _: &()) -> Self {
+        AreaStability::batch_with_delta(DEFAULT_DELTA, points, thres, img)
+    }
+}
+
+impl Feature for AreaStability {
+    fn value(&self, out: &mut Vec<f32>) {
+        out.push(self.variation());
+    }
+}
+
+impl Columns for AreaStability {
+    fn columns(out: &mut Vec<Column>) {
+        out.push(AreaStability::column(DEFAULT_DELTA));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Image;
+    use structures::Point;
+    use extract::cser::Incremental;
+
+    fn grow(a: &mut AreaStability, n: i32, thres: i32) {
+        let img: Image<u8> = Image::from_data(vec![], 0, 0);
+        let reg_img: Image<Option<usize>> = Image::from_data(vec![], 0, 0);
+        for _ in 0..n {
+            a.increment(Point { x: 0, y: 0 }, thres, &img, &reg_img);
+        }
+    }
+
+    #[test]
+    fn growth_within_delta() {
+        let mut a: AreaStability = Incremental::init(Point { x: 0, y: 0 }, 0, 10, &());
+        grow(&mut a, 3, 10);
+        grow(&mut a, 4, 13);
+
+        assert_eq!(a.area(), 8);
+        assert_eq!(a.variation(), 1.0f32);
+    }
+
+    #[test]
+    fn growth_older_than_delta_is_settled() {
+        let mut a: AreaStability = Incremental::init(Point { x: 0, y: 0 }, 0, 10, &());
+        grow(&mut a, 5, 10);
+        grow(&mut a, 2, 20);
+
+        assert_eq!(a.area(), 8);
+        assert_eq!(a.variation(), 2.0f32 / 8.0f32);
+    }
+
+    #[test]
+    fn merge_sums_histories() {
+        let img: Image<u8> = Image::from_data(vec![], 0, 0);
+        let reg_img: Image<Option<usize>> = Image::from_data(vec![], 0, 0);
+
+        let mut a: AreaStability = Incremental::init(Point { x: 0, y: 0 }, 0, 10, &());
+        grow(&mut a, 3, 12);
+
+        let mut b: AreaStability = Incremental::init(Point { x: 0, y: 0 }, 1, 12, &());
+        grow(&mut b, 1, 16);
+
+        a.merge(&b, 16, &img, &reg_img);
+
+        // growth at level 10 is older than 16 - 5
+        assert_eq!(a.area(), 6);
+        assert_eq!(a.variation(), 5.0f32 / 6.0f32);
+    }
+
+    #[test]
+    fn wider_delta_keeps_more_growth() {
+        let mut a = AreaStability::with_delta(10, 10);
+        grow(&mut a, 5, 10);
+        grow(&mut a, 2, 20);
+
+        assert_eq!(a.variation(), 2.0f32 / 8.0f32);
+
+        let mut b = AreaStability::with_delta(10, 10);
+        grow(&mut b, 5, 10);
+        grow(&mut b, 2, 15);
+
+        assert_eq!(b.variation(), 1.0f32);
+        assert_eq!(AreaStability::column(10).name, "area_variation_10");
+    }
+}
@@ -1,4 +1,7 @@
 use super::Feature;
+use super::Batch;
+use super::mask::bounds_of;
+use super::schema::{Column, Columns};
 use image::Image;
 use extract::cser::Incremental;
 use structures::{Point, Rect};
@@ -19,7 +22,9 @@ impl AspectRatio {
 }
 
 impl Incremental for AspectRatio {
-    fn init(p: Point, _: usize, _: i32) -> AspectRatio {
+    type Params = ();
+
+    fn init(p: Point, _: usize, _: i32, _: &()) -> AspectRatio {
         AspectRatio {
             bounds: Rect(p, p)
         }
@@ -34,12 +39,24 @@ impl Incremental for AspectRatio {
     }
 }
 
+impl Batch for AspectRatio {
+    fn batch(points: &[Point], _: usize, _: i32, _: &Image<u8>, _: &()) -> Self {
+        AspectRatio::from_bounds(bounds_of(points))
+    }
+}
+
 impl Feature for AspectRatio {
     fn value(&self, out: &mut Vec<f32>) {
         out.push((self.bounds.width() as f32) / (self.bounds.height() as f32));
     }
 }
 
+impl Columns for AspectRatio {
+    fn columns(out: &mut Vec<Column>) {
+        out.push(Column::new("aspect_ratio", Some(0.0f32), None));
+    }
+}
+
 #[cfg(test)]
 mod test {
     pub use image::Image;
@@ -50,7 +67,7 @@ mod test {
     describe! aspect_ratio {
         describe! init {
             before_each {
-                let ar: AspectRatio = AspectRatio::init(Point { x: 6, y: 3 }, 0, 0);
+                let ar: AspectRatio = AspectRatio::init(Point { x: 6, y: 3 }, 0, 0, &());
             }
 
             it "should create aspect ratio feature with value `1`" {
@@ -0,0 +1,13 @@
+use image::Image;
+use structures::Point;
+use extract::cser::Incremental;
+
+/// Reference computation of an incremental feature from the full set of
+/// region points. Used to check incremental implementations, so it should
+/// be written as straightforwardly as possible rather than efficiently.
+///
+/// `thres` is the last threshold at which the region has changed, `params`
+/// are the ones the incremental feature was created with.
+pub trait Batch : Incremental + Sized {
+    fn batch(points: &[Point], reg_idx: usize, thres: i32, img: &Image<u8>, params: &Self::Params) -> Self;
+}
@@ -0,0 +1,94 @@
+use structures::Point;
+
+use super::Feature;
+use super::{SecondStage, Frame};
+use super::mask::RegionMask;
+use super::schema::{Column, Columns};
+
+// largest possible Sobel gradient magnitude of an 8-bit image
+static MAX_GRADIENT: f32 = 1442.5f32;
+
+/// Mean gradient magnitude of the original image along the outer boundary
+/// of a region. Printed glyphs have sharp edges, while shadows and blurred
+/// textures have soft ones.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoundaryGradient {
+    mean: f32
+}
+
+impl BoundaryGradient {
+    pub fn mean(&self) -> f32 {
+        self.mean
+    }
+}
+
+impl SecondStage for BoundaryGradient {
+    fn compute(points: &[Point], frame: &Frame) -> Self {
+        let m = RegionMask::from_points(points, 1);
+
+        let mut sum = 0.0f32;
+        let mut count = 0;
+
+        // background pixels of the padded mask 4-connected to the region
+        for y in 0..(m.height() as i32) {
+            for x in 0..(m.width() as i32) {
+                let is_boundary = !m.get(x, y) && (
+                    m.get(x - 1, y) || m.get(x + 1, y) ||
+                    m.get(x, y - 1) || m.get(x, y + 1));
+
+                let (ix, iy) = (x + m.origin.x, y + m.origin.y);
+                if is_boundary && frame.gradient.inside(ix, iy) {
+                    sum += frame.gradient[(ix, iy)];
+                    count += 1;
+                }
+            }
+        }
+
+        BoundaryGradient {
+            mean: if count > 0 { sum / (count as f32) } else { 0.0f32 }
+        }
+    }
+}
+
+impl Feature for BoundaryGradient {
+    fn value(&self, out: &mut Vec<f32>) {
+        out.push(self.mean);
+    }
+}
+
+impl Columns for BoundaryGradient {
+    fn columns(out: &mut Vec<Column>) {
+        out.push(Column::new("boundary_gradient", Some(0.0f32), Some(MAX_GRADIENT)));
+    }
+}
+
+#[test]
+fn only_outer_boundary_pixels_are_sampled() {
+    use image::Image;
+
+    // region is the 3x3 block in the top-left corner, its outer boundary
+    // inside the image is the column x = 3 and the row y = 3 without the
+    // diagonal corner (3, 3)
+    let img: Image<u8> = Image::from_data(vec![
+        10, 10, 10, 0,
+        10, 10, 10, 0,
+        10, 10, 10, 0,
+         0,  0,  0, 0,
+    ], 4, 4);
+
+    let mut points = vec![];
+    for x in 0..3 {
+        for y in 0..3 {
+            points.push(Point { x: x, y: y });
+        }
+    }
+
+    let frame = Frame::new(&img);
+    let bg = BoundaryGradient::compute(&points, &frame);
+
+    let outer = [(3, 0), (3, 1), (3, 2), (0, 3), (1, 3), (2, 3)];
+    let expected = outer.iter()
+        .fold(0.0f32, |a, &(x, y)| a + frame.gradient[(x, y)]) / 6.0f32;
+
+    assert_eq!(bg.mean(), expected);
+}
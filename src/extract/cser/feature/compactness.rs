@@ -3,22 +3,29 @@ use structures::Point;
 use image::Image;
 
 use super::Feature;
+use super::Batch;
+use super::mask::RegionMask;
+use super::schema::{Column, Columns};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Compactness {
     perimeter: i32,
     area: i32,
+    reg_idx: usize
 }
 
 impl Incremental for Compactness {
-    fn init(_: Point, _: usize, _: i32) -> Self {
+    type Params = ();
+
+    fn init(_: Point, reg_idx: usize, _: i32, _: &()) -> Self {
         Compactness {
-            perimeter: 1,
-            area: 1
+            perimeter: 4,
+            area: 1,
+            reg_idx: reg_idx
         }
     }
 
-    fn increment(&mut self, p: Point, _: i32,   img: &Image<u8>,  _: &Image<Option<usize>>) {
+    fn increment(&mut self, p: Point, _: i32,   _: &Image<u8>,  reg_img: &Image<Option<usize>>) {
         self.area += 1;
 
         let mut sum = 0;
@@ -28,18 +35,16 @@ impl Incremental for Compactness {
             (p.x + 1, p.y), (p.x, p.y + 1)
         ];
 
-        let pv = img[(p.x, p.y)];
         for n in ns.iter() {
             let (x, y) = *n;
-            if img.inside(x, y) {
-                let qv = img[(x, y)];
-                if qv <= pv {
-                    sum += 1;
-                }
+            if reg_img.inside(x, y) && reg_img[(x, y)] == Some(self.reg_idx) {
+                sum += 1;
             }
         }
 
-        self.perimeter += 4 - sum;
+        // every adjacent region pixel removes one edge from
+        // the region and one edge from the new point
+        self.perimeter += 4 - 2 * sum;
     }
 
     fn merge(&mut self, other: &Self, _: i32, _: &Image<u8>, _: &Image<Option<usize>>) {
@@ -48,8 +53,117 @@ impl Incremental for Compactness {
     }
 }
 
+impl Batch for Compactness {
+    fn batch(points: &[Point], reg_idx: usize, _: i32, _: &Image<u8>, _: &()) -> Self {
+        let m = RegionMask::from_points(points, 1);
+        let mut perimeter = 0;
+
+        for p in points {
+            let (x, y) = (p.x - m.origin.x, p.y - m.origin.y);
+            for &(dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)].iter() {
+                if !m.get(x + dx, y + dy) {
+                    perimeter += 1;
+                }
+            }
+        }
+
+        Compactness {
+            perimeter: perimeter,
+            area: points.len() as i32,
+            reg_idx: reg_idx
+        }
+    }
+}
+
 impl Feature for Compactness {
     fn value(&self, out: &mut Vec<f32>) {
         out.push((self.perimeter as f32) / (self.area as f32));
     }
 }
+
+impl Columns for Compactness {
+    fn columns(out: &mut Vec<Column>) {
+        out.push(Column::new("compactness", Some(0.0f32), None));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::Image;
+    use structures::Point;
+    use extract::cser::Incremental;
+    use extract::cser::feature::{Feature, Batch};
+    use super::Compactness;
+
+    /// Adds points of `rows` one by one in reading order, the way the
+    /// detector does for a region growing at a single threshold.
+    fn grow(rows: &[&str]) -> (Compactness, Vec<Point>) {
+        let mut points = vec![];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == 'x' {
+                    points.push(Point { x: x as i32, y: y as i32 });
+                }
+            }
+        }
+
+        let img: Image<u8> = Image::from_data(vec![0; 16], 4, 4);
+        let mut reg_img: Image<Option<usize>> = Image::from_data(vec![None; 16], 4, 4);
+        let mut c: Compactness = Incremental::init(points[0], 0, 0, &());
+        reg_img.set_pixel(points[0].x, points[0].y, Some(0));
+        for p in &points[1..] {
+            c.increment(*p, 0, &img, &reg_img);
+            reg_img.set_pixel(p.x, p.y, Some(0));
+        }
+        (c, points)
+    }
+
+    fn value(c: &Compactness) -> f32 {
+        let mut v = vec![];
+        c.value(&mut v);
+        v[0]
+    }
+
+    fn batch(points: &[Point]) -> f32 {
+        let img: Image<u8> = Image::from_data(vec![0; 16], 4, 4);
+        value(&Compactness::batch(points, 0, 0, &img, &()))
+    }
+
+    #[test]
+    fn single_pixel() {
+        let (c, points) = grow(&["x"]);
+        assert_eq!(value(&c), 4.0f32);
+        assert_eq!(batch(&points), 4.0f32);
+    }
+
+    #[test]
+    fn block() {
+        let (c, points) = grow(&[
+            "xx",
+            "xx",
+        ]);
+        assert_eq!(value(&c), 8.0f32 / 4.0f32);
+        assert_eq!(batch(&points), 8.0f32 / 4.0f32);
+    }
+
+    #[test]
+    fn ring_counts_inner_boundary() {
+        let (c, points) = grow(&[
+            "xxx",
+            "x.x",
+            "xxx",
+        ]);
+        assert_eq!(value(&c), 16.0f32 / 8.0f32);
+        assert_eq!(batch(&points), 16.0f32 / 8.0f32);
+    }
+
+    #[test]
+    fn merge_adds_perimeters() {
+        let img: Image<u8> = Image::from_data(vec![0; 16], 4, 4);
+        let reg_img: Image<Option<usize>> = Image::from_data(vec![None; 16], 4, 4);
+        let mut a: Compactness = Incremental::init(Point { x: 0, y: 0 }, 0, 0, &());
+        let b: Compactness = Incremental::init(Point { x: 2, y: 0 }, 1, 0, &());
+        a.merge(&b, 0, &img, &reg_img);
+        assert_eq!(value(&a), 8.0f32 / 2.0f32);
+    }
+}
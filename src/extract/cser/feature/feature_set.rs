@@ -0,0 +1,336 @@
+use image::Image;
+use structures::Point;
+use extract::cser::Incremental;
+
+use super::{Feature, Batch};
+use super::schema::{Column, Columns, Schema};
+use super::{AspectRatio, Compactness, NumHoles, HorizontalCrossings, AreaStability, Orientation};
+use super::area_stability::DEFAULT_DELTA;
+
+/// Feature of a runtime composed `FeatureSet`. Written in layouts by its
+/// name, followed by the parameter of configurable features, e.g.
+/// `area_stability:10`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FeatureKind {
+    AspectRatio,
+    Compactness,
+    HorizontalCrossings,
+    NumHoles,
+    /// Area stability over the given number of gray levels.
+    AreaStability(i32),
+    Orientation
+}
+
+impl FeatureKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            FeatureKind::AspectRatio => "aspect_ratio",
+            FeatureKind::Compactness => "compactness",
+            FeatureKind::HorizontalCrossings => "horizontal_crossings",
+            FeatureKind::NumHoles => "num_holes",
+            FeatureKind::AreaStability(_) => "area_stability",
+            FeatureKind::Orientation => "orientation"
+        }
+    }
+
+    /// Feature by its name, with parameters set to their defaults.
+    pub fn from_name(name: &str) -> Option<FeatureKind> {
+        match name {
+            "aspect_ratio" => Some(FeatureKind::AspectRatio),
+            "compactness" => Some(FeatureKind::Compactness),
+            "horizontal_crossings" => Some(FeatureKind::HorizontalCrossings),
+            "num_holes" => Some(FeatureKind::NumHoles),
+            "area_stability" => Some(FeatureKind::AreaStability(DEFAULT_DELTA)),
+            "orientation" => Some(FeatureKind::Orientation),
+            _ => None
+        }
+    }
+
+    /// Parses a layout entry, i.e. feature name optionally followed by
+    /// `:` and its parameter.
+    pub fn parse(entry: &str) -> Result<FeatureKind, LayoutError> {
+        let mut parts = entry.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        let kind = try!(FeatureKind::from_name(name).ok_or(LayoutError::UnknownFeature(name.to_string())));
+
+        match (kind, parts.next().map(|p| p.trim())) {
+            (_, None) => Ok(kind),
+            (FeatureKind::AreaStability(_), Some(param)) => match param.parse::<i32>() {
+                Ok(delta) if delta > 0 => Ok(FeatureKind::AreaStability(delta)),
+                _ => Err(LayoutError::InvalidParameter(entry.to_string()))
+            },
+            (_, Some(_)) => Err(LayoutError::InvalidParameter(entry.to_string()))
+        }
+    }
+
+    pub fn columns(&self, out: &mut Vec<Column>) {
+        match *self {
+            FeatureKind::AspectRatio => AspectRatio::columns(out),
+            FeatureKind::Compactness => Compactness::columns(out),
+            FeatureKind::HorizontalCrossings => HorizontalCrossings::columns(out),
+            FeatureKind::NumHoles => NumHoles::columns(out),
+            FeatureKind::AreaStability(delta) => out.push(AreaStability::column(delta)),
+            FeatureKind::Orientation => Orientation::columns(out)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum AnyFeature {
+    AspectRatio(AspectRatio),
+    Compactness(Compactness),
+    HorizontalCrossings(HorizontalCrossings),
+    NumHoles(NumHoles),
+    AreaStability(AreaStability),
+    Orientation(Orientation)
+}
+
+impl AnyFeature {
+    pub fn init(kind: FeatureKind, p: Point, reg_idx: usize, thres: i32) -> AnyFeature {
+        match kind {
+            FeatureKind::AspectRatio => AnyFeature::AspectRatio(Incremental::init(p, reg_idx, thres, &())),
+            FeatureKind::Compactness => AnyFeature::Compactness(Incremental::init(p, reg_idx, thres, &())),
+            FeatureKind::HorizontalCrossings => AnyFeature::HorizontalCrossings(Incremental::init(p, reg_idx, thres, &())),
+            FeatureKind::NumHoles => AnyFeature::NumHoles(Incremental::init(p, reg_idx, thres, &())),
+            FeatureKind::AreaStability(delta) => AnyFeature::AreaStability(AreaStability::with_delta(delta, thres)),
+            FeatureKind::Orientation => AnyFeature::Orientation(Incremental::init(p, reg_idx, thres, &()))
+        }
+    }
+
+    pub fn batch(kind: FeatureKind, points: &[Point], reg_idx: usize, thres: i32, img: &Image<u8>) -> AnyFeature {
+        match kind {
+            FeatureKind::AspectRatio => AnyFeature::AspectRatio(Batch::batch(points, reg_idx, thres, img, &())),
+            FeatureKind::Compactness => AnyFeature::Compactness(Batch::batch(points, reg_idx, thres, img, &())),
+            FeatureKind::HorizontalCrossings => AnyFeature::HorizontalCrossings(Batch::batch(points, reg_idx, thres, img, &())),
+            FeatureKind::NumHoles => AnyFeature::NumHoles(Batch::batch(points, reg_idx, thres, img, &())),
+            FeatureKind::AreaStability(delta) =>
+                AnyFeature::AreaStability(AreaStability::batch_with_delta(delta, points, thres, img)),
+            FeatureKind::Orientation => AnyFeature::Orientation(Batch::batch(points, reg_idx, thres, img, &()))
+        }
+    }
+
+    pub fn kind(&self) -> FeatureKind {
+        match *self {
+            AnyFeature::AspectRatio(_) => FeatureKind::AspectRatio,
+            AnyFeature::Compactness(_) => FeatureKind::Compactness,
+            AnyFeature::HorizontalCrossings(_) => FeatureKind::HorizontalCrossings,
+            AnyFeature::NumHoles(_) => FeatureKind::NumHoles,
+            AnyFeature::AreaStability(ref f) => FeatureKind::AreaStability(f.delta()),
+            AnyFeature::Orientation(_) => FeatureKind::Orientation
+        }
+    }
+
+    fn increment(&mut self, p: Point, thres: i32, img: &Image<u8>, reg_img: &Image<Option<usize>>) {
+        match *self {
+            AnyFeature::AspectRatio(ref mut f) => f.increment(p, thres, img, reg_img),
+            AnyFeature::Compactness(ref mut f) => f.increment(p, thres, img, reg_img),
+            AnyFeature::HorizontalCrossings(ref mut f) => f.increment(p, thres, img, reg_img),
+            AnyFeature::NumHoles(ref mut f) => f.increment(p, thres, img, reg_img),
+            AnyFeature::AreaStability(ref mut f) => f.increment(p, thres, img, reg_img),
+            AnyFeature::Orientation(ref mut f) => f.increment(p, thres, img, reg_img)
+        }
+    }
+
+    fn merge(&mut self, other: &AnyFeature, thres: i32, img: &Image<u8>, reg_img: &Image<Option<usize>>) {
+        match (self, other) {
+            (&mut AnyFeature::AspectRatio(ref mut a), &AnyFeature::AspectRatio(ref b)) => a.merge(b, thres, img, reg_img),
+            (&mut AnyFeature::Compactness(ref mut a), &AnyFeature::Compactness(ref b)) => a.merge(b, thres, img, reg_img),
+            (&mut AnyFeature::HorizontalCrossings(ref mut a), &AnyFeature::HorizontalCrossings(ref b)) =>
+                a.merge(b, thres, img, reg_img),
+            (&mut AnyFeature::NumHoles(ref mut a), &AnyFeature::NumHoles(ref b)) => a.merge(b, thres, img, reg_img),
+            (&mut AnyFeature::AreaStability(ref mut a), &AnyFeature::AreaStability(ref b)) => a.merge(b, thres, img, reg_img),
+            (&mut AnyFeature::Orientation(ref mut a), &AnyFeature::Orientation(ref b)) => a.merge(b, thres, img, reg_img),
+            _ => panic!("can't merge features of different kinds")
+        }
+    }
+
+    pub fn value(&self, out: &mut Vec<f32>) {
+        match *self {
+            AnyFeature::AspectRatio(ref f) => Feature::value(f, out),
+            AnyFeature::Compactness(ref f) => f.value(out),
+            AnyFeature::HorizontalCrossings(ref f) => f.value(out),
+            AnyFeature::NumHoles(ref f) => f.value(out),
+            AnyFeature::AreaStability(ref f) => f.value(out),
+            AnyFeature::Orientation(ref f) => f.value(out)
+        }
+    }
+}
+
+pub static DEFAULT_LAYOUT: &'static str = "aspect_ratio,compactness,horizontal_crossings,num_holes";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    UnknownFeature(String),
+    InvalidParameter(String),
+    Empty
+}
+
+/// Parses comma separated list of feature names, e.g.
+/// `aspect_ratio,num_holes,area_stability:10`.
+pub fn parse_layout(names: &str) -> Result<Vec<FeatureKind>, LayoutError> {
+    let mut layout = vec![];
+    for entry in names.split(',').map(|n| n.trim()).filter(|n| n.len() > 0) {
+        layout.push(try!(FeatureKind::parse(entry)));
+    }
+
+    if layout.len() == 0 {
+        return Err(LayoutError::Empty);
+    }
+
+    Ok(layout)
+}
+
+/// Set of features composed at runtime. Unlike feature tuples, trying a
+/// new combination of features doesn't require recompilation. Feature sets
+/// are created with their layout as `Incremental::Params`.
+#[derive(Debug, Clone)]
+pub struct FeatureSet {
+    features: Vec<AnyFeature>
+}
+
+impl FeatureSet {
+    pub fn features<'a>(&'a self) -> &'a [AnyFeature] {
+        &self.features[..]
+    }
+
+    /// Columns of feature sets with `layout`.
+    pub fn schema(layout: &[FeatureKind]) -> Schema {
+        let mut columns = vec![];
+        for kind in layout {
+            kind.columns(&mut columns);
+        }
+        Schema { columns: columns }
+    }
+}
+
+impl Incremental for FeatureSet {
+    type Params = Vec<FeatureKind>;
+
+    fn init(p: Point, reg_idx: usize, thres: i32, layout: &Vec<FeatureKind>) -> Self {
+        assert!(layout.len() > 0, "feature set layout is empty");
+        FeatureSet {
+            features: layout.iter()
+                .map(|kind| AnyFeature::init(*kind, p, reg_idx, thres))
+                .collect()
+        }
+    }
+
+    fn increment(&mut self, p: Point, thres: i32, img: &Image<u8>, reg_img: &Image<Option<usize>>) {
+        for f in self.features.iter_mut() {
+            f.increment(p, thres, img, reg_img);
+        }
+    }
+
+    fn merge(&mut self, other: &Self, thres: i32, img: &Image<u8>, reg_img: &Image<Option<usize>>) {
+        assert_eq!(self.features.len(), other.features.len());
+        for (a, b) in self.features.iter_mut().zip(other.features.iter()) {
+            a.merge(b, thres, img, reg_img);
+        }
+    }
+}
+
+impl Batch for FeatureSet {
+    fn batch(points: &[Point], reg_idx: usize, thres: i32, img: &Image<u8>, layout: &Vec<FeatureKind>) -> Self {
+        FeatureSet {
+            features: layout.iter()
+                .map(|kind| AnyFeature::batch(*kind, points, reg_idx, thres, img))
+                .collect()
+        }
+    }
+}
+
+impl Feature for FeatureSet {
+    fn value(&self, out: &mut Vec<f32>) {
+        for f in self.features.iter() {
+            f.value(out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Image;
+    use structures::Point;
+    use extract::cser::{Feature, Incremental};
+    use extract::cser::feature::{AspectRatio, NumHoles, Schema};
+
+    #[test]
+    fn parse_layout_test() {
+        assert_eq!(
+            parse_layout("aspect_ratio, num_holes"),
+            Ok(vec![FeatureKind::AspectRatio, FeatureKind::NumHoles])
+        );
+        assert_eq!(
+            parse_layout("aspect_ratio,foo"),
+            Err(LayoutError::UnknownFeature("foo".to_string()))
+        );
+        assert_eq!(parse_layout(""), Err(LayoutError::Empty));
+    }
+
+    #[test]
+    fn parse_area_stability_delta() {
+        assert_eq!(
+            parse_layout("area_stability, area_stability:10"),
+            Ok(vec![FeatureKind::AreaStability(5), FeatureKind::AreaStability(10)])
+        );
+        assert_eq!(
+            parse_layout("area_stability:0"),
+            Err(LayoutError::InvalidParameter("area_stability:0".to_string()))
+        );
+        assert_eq!(
+            parse_layout("num_holes:2"),
+            Err(LayoutError::InvalidParameter("num_holes:2".to_string()))
+        );
+
+        let mut columns = vec![];
+        FeatureKind::AreaStability(10).columns(&mut columns);
+        assert_eq!(columns[0].name, "area_variation_10");
+    }
+
+    #[test]
+    fn feature_set_matches_tuple() {
+        let img: Image<u8> = Image::from_data(vec![0; 4], 2, 2);
+        let reg_img: Image<Option<usize>> = Image::from_data(vec![Some(0), None, None, None], 2, 2);
+        let layout = parse_layout("aspect_ratio,num_holes").unwrap();
+
+        let mut fs: FeatureSet = Incremental::init(Point { x: 0, y: 0 }, 0, 0, &layout);
+        let mut tuple: (AspectRatio, NumHoles) = Incremental::init(Point { x: 0, y: 0 }, 0, 0, &());
+
+        fs.increment(Point { x: 1, y: 0 }, 0, &img, &reg_img);
+        tuple.increment(Point { x: 1, y: 0 }, 0, &img, &reg_img);
+
+        let (mut a, mut b) = (vec![], vec![]);
+        fs.value(&mut a);
+        tuple.value(&mut b);
+
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn schema_of_layout() {
+        let layout = parse_layout("num_holes,aspect_ratio").unwrap();
+
+        assert_eq!(FeatureSet::schema(&layout), Schema::of::<(NumHoles, AspectRatio)>());
+    }
+
+    #[test]
+    #[should_panic(expected = "feature set layout is empty")]
+    fn init_with_empty_layout() {
+        let _: FeatureSet = Incremental::init(Point { x: 0, y: 0 }, 0, 0, &vec![]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn merge_of_different_layouts() {
+        let img: Image<u8> = Image::from_data(vec![0; 4], 2, 2);
+        let reg_img: Image<Option<usize>> = Image::from_data(vec![Some(0), Some(1), None, None], 2, 2);
+
+        let (long, short) = (parse_layout("aspect_ratio,num_holes").unwrap(), parse_layout("aspect_ratio").unwrap());
+
+        let mut a: FeatureSet = Incremental::init(Point { x: 0, y: 0 }, 0, 0, &long);
+        let b: FeatureSet = Incremental::init(Point { x: 1, y: 0 }, 1, 0, &short);
+
+        a.merge(&b, 0, &img, &reg_img);
+    }
+}
@@ -2,113 +2,61 @@ use image::Image;
 use structures::Point;
 use extract::cser::Incremental;
 
-use super::Feature;
-
-impl<A: Incremental, B: Incremental> Incremental for (A, B) {
-    fn init(p: Point, reg_idx: usize, thres: i32) -> Self {
-        (A::init(p, reg_idx, thres), B::init(p, reg_idx, thres))
-    }
-
-    fn increment(&mut self, p: Point, thres: i32, img: &Image<u8>,  reg_img: &Image<Option<usize>>) {
-        self.0.increment(p, thres, img, reg_img);
-        self.1.increment(p, thres, img, reg_img);
-    }
-
-    fn merge(&mut self, other: &Self, thres: i32, img: &Image<u8>, reg_image: &Image<Option<usize>>) {
-        self.0.merge(&other.0, thres, img, reg_image);
-        self.1.merge(&other.1, thres, img, reg_image);
-    }
-}
-
-impl<A: Incremental + Feature, B: Incremental + Feature> Feature for (A, B) {
-    fn value(&self, out: &mut Vec<f32>) {
-        self.0.value(out);
-        self.1.value(out);
-    }
-}
-
-impl<A: Incremental, B: Incremental, C: Incremental> Incremental for (A, B, C) {
-    fn init(p: Point, reg_idx: usize, thres: i32) -> Self {
-        (A::init(p, reg_idx, thres), B::init(p, reg_idx, thres), C::init(p, reg_idx, thres))
-    }
-
-    fn increment(&mut self, p: Point, thres: i32,   img: &Image<u8>,  reg_img: &Image<Option<usize>>) {
-        self.0.increment(p, thres, img, reg_img);
-        self.1.increment(p, thres, img, reg_img);
-        self.2.increment(p, thres, img, reg_img);
-    }
-
-    fn merge(&mut self, other: &Self, thres: i32, img: &Image<u8>, reg_image: &Image<Option<usize>>) {
-        self.0.merge(&other.0, thres, img, reg_image);
-        self.1.merge(&other.1, thres, img, reg_image);
-        self.2.merge(&other.2, thres, img, reg_image);
+use super::{Feature, Batch, SecondStage, Frame};
+use super::schema::{Column, Columns};
+
+macro_rules! feature_tuple {
+    ($($t:ident : $i:tt),+) => {
+        impl<$($t: Incremental<Params = ()>),+> Incremental for ($($t,)+) {
+            type Params = ();
+
+            fn init(p: Point, reg_idx: usize, thres: i32, _: &()) -> Self {
+                ($($t::init(p, reg_idx, thres, &()),)+)
+            }
+
+            fn increment(&mut self, p: Point, thres: i32, img: &Image<u8>, reg_img: &Image<Option<usize>>) {
+                $(self.$i.increment(p, thres, img, reg_img);)+
+            }
+
+            fn merge(&mut self, other: &Self, thres: i32, img: &Image<u8>, reg_img: &Image<Option<usize>>) {
+                $(self.$i.merge(&other.$i, thres, img, reg_img);)+
+            }
+        }
+
+        impl<$($t: Feature),+> Feature for ($($t,)+) {
+            fn value(&self, out: &mut Vec<f32>) {
+                $(self.$i.value(out);)+
+            }
+        }
+
+        impl<$($t: Columns),+> Columns for ($($t,)+) {
+            fn columns(out: &mut Vec<Column>) {
+                $($t::columns(out);)+
+            }
+        }
+
+        impl<$($t: Batch + Incremental<Params = ()>),+> Batch for ($($t,)+) {
+            fn batch(points: &[Point], reg_idx: usize, thres: i32, img: &Image<u8>, _: &()) -> Self {
+                ($($t::batch(points, reg_idx, thres, img, &()),)+)
+            }
+        }
+
+        impl<$($t: SecondStage),+> SecondStage for ($($t,)+) {
+            fn compute(points: &[Point], frame: &Frame) -> Self {
+                ($($t::compute(points, frame),)+)
+            }
+        }
     }
 }
 
-impl<A: Incremental + Feature,
-     B: Incremental + Feature,
-     C: Incremental + Feature
-> Feature for (A, B, C) {
-    fn value(&self, out: &mut Vec<f32>) {
-        self.0.value(out);
-        self.1.value(out);
-        self.2.value(out);
-    }
-}
-
-impl<A: Incremental,
-     B: Incremental,
-     C: Incremental,
-     D: Incremental
-> Incremental for (A, B, C, D) {
-    fn init(p: Point, reg_idx: usize, thres: i32) -> Self {
-        (
-            A::init(p, reg_idx, thres),
-            B::init(p, reg_idx, thres),
-            C::init(p, reg_idx, thres),
-            D::init(p, reg_idx, thres)
-        )
-    }
-
-    fn increment(&mut self, p: Point, thres: i32, img: &Image<u8>,  reg_img: &Image<Option<usize>>) {
-        self.0.increment(p, thres, img, reg_img);
-        self.1.increment(p, thres, img, reg_img);
-        self.2.increment(p, thres, img, reg_img);
-        self.3.increment(p, thres, img, reg_img);
-    }
-
-    fn merge(&mut self, other: &Self, thres: i32, img: &Image<u8>, reg_image: &Image<Option<usize>>) {
-        self.0.merge(&other.0, thres, img, reg_image);
-        self.1.merge(&other.1, thres, img, reg_image);
-        self.2.merge(&other.2, thres, img, reg_image);
-        self.3.merge(&other.3, thres, img, reg_image);
-    }
-}
-
-impl<A: Incremental + Feature,
-     B: Incremental + Feature,
-     C: Incremental + Feature,
-     D: Incremental + Feature
-> Feature for (A, B, C, D) {
-    fn value(&self, out: &mut Vec<f32>) {
-        self.0.value(out);
-        self.1.value(out);
-        self.2.value(out);
-        self.3.value(out);
-    }
-}
-
-impl<A: Incremental + Feature,
-     B: Incremental + Feature,
-     C: Incremental + Feature,
-     D: Incremental + Feature,
-     E: Incremental + Feature
-> Feature for (A, B, C, D, E) {
-    fn value(&self, out: &mut Vec<f32>) {
-        self.0.value(out);
-        self.1.value(out);
-        self.2.value(out);
-        self.3.value(out);
-        self.4.value(out);
-    }
-}
+feature_tuple!(A: 0, B: 1);
+feature_tuple!(A: 0, B: 1, C: 2);
+feature_tuple!(A: 0, B: 1, C: 2, D: 3);
+feature_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4);
+feature_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+feature_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+feature_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+feature_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8);
+feature_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9);
+feature_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10);
+feature_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7, I: 8, J: 9, K: 10, L: 11);
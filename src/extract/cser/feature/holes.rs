@@ -0,0 +1,195 @@
+use image::Image;
+use structures::Point;
+
+use super::Feature;
+use super::{SecondStage, Frame};
+use super::mask::RegionMask;
+use super::schema::{Column, Columns};
+
+/// Holes of a region extracted explicitly, as 8-connected components of
+/// the background that don't touch the border of region bounding box.
+///
+/// Reports number of holes, their total area relative to region area and
+/// position of the largest hole's centroid relative to region bounds
+/// (`-1` if there are no holes).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Holes {
+    count: i32,
+    area_ratio: f32,
+    largest_pos: (f32, f32)
+}
+
+impl Holes {
+    pub fn count(&self) -> i32 {
+        self.count
+    }
+
+    pub fn area_ratio(&self) -> f32 {
+        self.area_ratio
+    }
+
+    pub fn largest_pos(&self) -> (f32, f32) {
+        self.largest_pos
+    }
+}
+
+impl SecondStage for Holes {
+    fn compute(points: &[Point], _: &Frame) -> Self {
+        let m = RegionMask::from_points(points, 1);
+        let (w, h) = (m.width() as i32, m.height() as i32);
+
+        // 0 - unvisited, 1 - region or outside background, 2.. - hole labels
+        let mut labels: Image<i32> = m.mask.map(|v| if *v { 1 } else { 0 });
+        flood_fill(&mut labels, Point { x: 0, y: 0 }, 1);
+
+        let mut holes: Vec<(i32, i64, i64)> = vec![];
+        for y in 0..h {
+            for x in 0..w {
+                if labels[(x, y)] == 0 {
+                    let label = holes.len() as i32 + 2;
+                    let area = flood_fill(&mut labels, Point { x: x, y: y }, label);
+                    holes.push((area, 0, 0));
+                }
+            }
+        }
+
+        for y in 0..h {
+            for x in 0..w {
+                let l = labels[(x, y)];
+                if l >= 2 {
+                    let hole = &mut holes[(l - 2) as usize];
+                    hole.1 += x as i64;
+                    hole.2 += y as i64;
+                }
+            }
+        }
+
+        // mask is padded by 1 pixel on each side
+        let (max_x, max_y) = (((w - 3) as f32).max(1.0f32), ((h - 3) as f32).max(1.0f32));
+
+        let total_area = holes.iter().fold(0, |a, hole| a + hole.0);
+        let largest_pos = holes.iter()
+            .max_by_key(|hole| hole.0)
+            .map(|hole| {
+                let cx = (hole.1 as f32) / (hole.0 as f32) - 1.0f32;
+                let cy = (hole.2 as f32) / (hole.0 as f32) - 1.0f32;
+                (cx / max_x, cy / max_y)
+            })
+            .unwrap_or((-1.0f32, -1.0f32));
+
+        Holes {
+            count: holes.len() as i32,
+            area_ratio: (total_area as f32) / (points.len() as f32),
+            largest_pos: largest_pos
+        }
+    }
+}
+
+impl Feature for Holes {
+    fn value(&self, out: &mut Vec<f32>) {
+        out.push(self.count as f32);
+        out.push(self.area_ratio);
+        out.push(self.largest_pos.0);
+        out.push(self.largest_pos.1);
+    }
+}
+
+impl Columns for Holes {
+    fn columns(out: &mut Vec<Column>) {
+        out.push(Column::new("holes_count", Some(0.0f32), None));
+        out.push(Column::new("holes_area_ratio", Some(0.0f32), None));
+        out.push(Column::new("holes_largest_x", Some(-1.0f32), Some(1.0f32)));
+        out.push(Column::new("holes_largest_y", Some(-1.0f32), Some(1.0f32)));
+    }
+}
+
+/// Fills 8-connected component of zero pixels containing `start`
+/// with `label`. Returns number of filled pixels.
+fn flood_fill(labels: &mut Image<i32>, start: Point, label: i32) -> i32 {
+    let mut stack = vec![start];
+    let mut area = 0;
+    labels.set_pixel(start.x, start.y, label);
+
+    while let Some(p) = stack.pop() {
+        area += 1;
+        for dx in -1..2 {
+            for dy in -1..2 {
+                let (x, y) = (p.x + dx, p.y + dy);
+                if labels.inside(x, y) && labels[(x, y)] == 0 {
+                    labels.set_pixel(x, y, label);
+                    stack.push(Point { x: x, y: y });
+                }
+            }
+        }
+    }
+
+    area
+}
+
+#[cfg(test)]
+mod test {
+    use image::Image;
+    use structures::Point;
+    use extract::cser::feature::{SecondStage, Frame};
+    use super::Holes;
+
+    fn holes(rows: &[&str]) -> Holes {
+        let mut points = vec![];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == 'x' {
+                    points.push(Point { x: x as i32, y: y as i32 });
+                }
+            }
+        }
+        let img: Image<u8> = Image::from_data(vec![], 0, 0);
+        Holes::compute(&points, &Frame::new(&img))
+    }
+
+    #[test]
+    fn solid_region_has_no_holes() {
+        let h = holes(&[
+            "xxx",
+            "xxx",
+        ]);
+        assert_eq!(h.count(), 0);
+        assert_eq!(h.area_ratio(), 0.0f32);
+        assert_eq!(h.largest_pos(), (-1.0f32, -1.0f32));
+    }
+
+    #[test]
+    fn eight() {
+        let h = holes(&[
+            "xxx",
+            "x.x",
+            "xxx",
+            "x.x",
+            "x.x",
+            "xxx",
+        ]);
+        assert_eq!(h.count(), 2);
+        assert_eq!(h.area_ratio(), 3.0f32 / 15.0f32);
+        assert_eq!(h.largest_pos(), (0.5f32, 0.7f32));
+    }
+
+    #[test]
+    fn background_touching_bounds_is_not_a_hole() {
+        let h = holes(&[
+            "xxx",
+            "x..",
+            "xxx",
+        ]);
+        assert_eq!(h.count(), 0);
+    }
+
+    #[test]
+    fn holes_are_8_connected() {
+        let h = holes(&[
+            "xxxx",
+            "x.xx",
+            "xx.x",
+            "xxxx",
+        ]);
+        assert_eq!(h.count(), 1);
+    }
+}
@@ -4,6 +4,9 @@ use std::collections::VecDeque;
 use image::Image;
 use structures::Point;
 use extract::cser::{Feature, Incremental};
+use extract::cser::feature::Batch;
+use extract::cser::feature::mask::RegionMask;
+use extract::cser::feature::schema::{Column, Columns};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct HorizontalCrossings {
@@ -13,8 +16,20 @@ pub struct HorizontalCrossings {
     reg_idx: usize
 }
 
+impl HorizontalCrossings {
+    fn crossings_at(&self, y: i32) -> i32 {
+        if y >= self.y_top && y <= self.y_btm {
+            self.num_crossings[(y - self.y_top) as usize]
+        } else {
+            0
+        }
+    }
+}
+
 impl Incremental for HorizontalCrossings {
-    fn init(p: Point, reg_idx: usize, _: i32) -> Self {
+    type Params = ();
+
+    fn init(p: Point, reg_idx: usize, _: i32, _: &()) -> Self {
         let mut nc: VecDeque<i32> = VecDeque::new();
         nc.push_back(2);
 
@@ -55,43 +70,43 @@ impl Incremental for HorizontalCrossings {
     }
 
     fn merge(&mut self, other: &HorizontalCrossings, _: i32, _: &Image<u8>, _: &Image<Option<usize>>) {
-        // intersection area
-        {
-            let a = max(self.y_top, other.y_top);
-            let b = min(self.y_btm, other.y_btm);
-            if b >= a {
-                for y in a...b {
-                    let is = y - self.y_top;
-                    let io = y - other.y_top;
-                    debug_assert!(is >= 0 && io >= 0);
-                    self.num_crossings[is as usize] += other.num_crossings[io as usize];
-                }
-            }
-        };
-
-        // add to top
-        {
-            let d = self.y_top - other.y_top;
-            if d > 0 {
-                for i in (0..d).rev() {
-                    self.num_crossings.push_front(other.num_crossings[i as usize]);
-                }
-            }
-        };
-        self.y_top = min(self.y_top, other.y_top);
-
-        // add to bottom
-        {
-            let d = other.y_btm - self.y_btm;
-            if d > 0 {
-                for i in (0..d).rev() {
-                    let io = other.num_crossings.len() as i32 - i - 1;
-                    debug_assert!(io >= 0);
-                    self.num_crossings.push_back(other.num_crossings[io as usize]);
+        // merged regions are not adjacent, so there may be a gap
+        // between them that is filled by the point being added
+        let top = min(self.y_top, other.y_top);
+        let btm = max(self.y_btm, other.y_btm);
+
+        let mut nc: VecDeque<i32> = VecDeque::with_capacity((btm - top + 1) as usize);
+        for y in top...btm {
+            nc.push_back(self.crossings_at(y) + other.crossings_at(y));
+        }
+
+        self.num_crossings = nc;
+        self.y_top = top;
+        self.y_btm = btm;
+    }
+}
+
+impl Batch for HorizontalCrossings {
+    fn batch(points: &[Point], reg_idx: usize, _: i32, _: &Image<u8>, _: &()) -> Self {
+        let m = RegionMask::from_points(points, 1);
+        let mut nc: VecDeque<i32> = VecDeque::new();
+
+        for y in 1..(m.height() as i32 - 1) {
+            let mut crossings = 0;
+            for x in 0..(m.width() as i32 - 1) {
+                if m.get(x, y) != m.get(x + 1, y) {
+                    crossings += 1;
                 }
             }
-        };
-        self.y_btm = max(self.y_btm, other.y_btm);
+            nc.push_back(crossings);
+        }
+
+        HorizontalCrossings {
+            num_crossings: nc,
+            y_top: m.origin.y + 1,
+            y_btm: m.origin.y + m.height() as i32 - 2,
+            reg_idx: reg_idx
+        }
     }
 }
 
@@ -121,6 +136,12 @@ impl Feature for HorizontalCrossings {
     }
 }
 
+impl Columns for HorizontalCrossings {
+    fn columns(out: &mut Vec<Column>) {
+        out.push(Column::new("horizontal_crossings", Some(0.0f32), None));
+    }
+}
+
 #[test]
 fn merge_with_intersection() {
 
@@ -0,0 +1,67 @@
+use image::Image;
+use structures::{Point, Rect};
+
+/// Binary mask of a region's points cropped to its bounding box.
+/// The mask is padded with `pad` background pixels on every side.
+#[derive(Clone)]
+pub struct RegionMask {
+    pub origin: Point,
+    pub mask: Image<bool>
+}
+
+impl RegionMask {
+    pub fn from_points(points: &[Point], pad: i32) -> RegionMask {
+        let bounds = bounds_of(points);
+        let origin = Point { x: bounds.0.x - pad, y: bounds.0.y - pad };
+        let width = (bounds.width() + 2 * pad) as usize;
+        let height = (bounds.height() + 2 * pad) as usize;
+
+        let mut mask = Image::from_data(vec![false; width * height], width, height);
+        for p in points {
+            mask.set_pixel(p.x - origin.x, p.y - origin.y, true);
+        }
+
+        RegionMask { origin: origin, mask: mask }
+    }
+
+    pub fn width(&self) -> usize {
+        self.mask.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.mask.height()
+    }
+
+    /// Checks if mask pixel with local coordinates `(x, y)` belongs to the region.
+    /// Coordinates outside of the mask are treated as background.
+    pub fn get(&self, x: i32, y: i32) -> bool {
+        self.mask.inside(x, y) && self.mask[(x, y)]
+    }
+}
+
+pub fn bounds_of(points: &[Point]) -> Rect {
+    debug_assert!(points.len() > 0);
+    points.iter()
+        .skip(1)
+        .fold(Rect(points[0], points[0]), |r, p| r.expand(Rect(*p, *p)))
+}
+
+#[test]
+fn from_points_test() {
+    let points = vec![
+        Point { x: 3, y: 5 },
+        Point { x: 4, y: 5 },
+        Point { x: 4, y: 6 },
+    ];
+
+    let m = RegionMask::from_points(&points, 1);
+
+    assert_eq!(m.origin, Point { x: 2, y: 4 });
+    assert_eq!((m.width(), m.height()), (4, 4));
+    assert_eq!(m.get(1, 1), true);
+    assert_eq!(m.get(2, 1), true);
+    assert_eq!(m.get(1, 2), false);
+    assert_eq!(m.get(2, 2), true);
+    assert_eq!(m.get(0, 0), false);
+    assert_eq!(m.get(-1, 0), false);
+}
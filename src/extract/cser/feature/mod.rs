@@ -1,12 +1,33 @@
 mod aspect_ratio;
 mod feature;
 mod feature_tuple;
+mod feature_set;
 mod compactness;
 mod num_holes;
 mod horizontal_crossings;
+mod area_stability;
+mod orientation;
+mod batch;
+mod second_stage;
+mod stroke_width;
+mod boundary_gradient;
+mod holes;
+mod schema;
+pub mod mask;
 
 pub use self::feature::Feature;
+pub use self::schema::{Schema, Column, Columns, SchemaMismatch};
+pub use self::feature_set::{FeatureSet, FeatureKind, AnyFeature, LayoutError, DEFAULT_LAYOUT};
+pub use self::feature_set::parse_layout;
+pub use self::batch::Batch;
+pub use self::second_stage::{SecondStage, Frame, SecondStageKind, AnySecondStage, SecondStageSet};
+pub use self::second_stage::parse_second_stage;
 pub use self::aspect_ratio::AspectRatio;
 pub use self::compactness::Compactness;
 pub use self::num_holes::NumHoles;
 pub use self::horizontal_crossings::HorizontalCrossings;
+pub use self::area_stability::{AreaStability, DEFAULT_DELTA};
+pub use self::orientation::Orientation;
+pub use self::stroke_width::StrokeWidth;
+pub use self::boundary_gradient::BoundaryGradient;
+pub use self::holes::Holes;
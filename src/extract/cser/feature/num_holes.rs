@@ -1,7 +1,9 @@
 use image::Image;
 use structures::Point;
 use extract::cser::Incremental;
-use extract::cser::feature::Feature;
+use extract::cser::feature::{Feature, Batch};
+use extract::cser::feature::mask::RegionMask;
+use extract::cser::feature::schema::{Column, Columns};
 
 type Matrix = [[i32;3];3];
 
@@ -12,8 +14,11 @@ pub struct NumHoles {
 }
 
 impl Incremental for NumHoles {
-    fn init(_: Point, reg_idx: usize, _: i32) -> Self {
-        NumHoles { genus: 0.0f32, reg_idx: reg_idx }
+    type Params = ();
+
+    fn init(_: Point, reg_idx: usize, _: i32, _: &()) -> Self {
+        // euler number of a single pixel
+        NumHoles { genus: 1.0f32, reg_idx: reg_idx }
     }
 
     fn increment(&mut self, p: Point, _: i32,   _: &Image<u8>,  reg_image: &Image<Option<usize>>) {
@@ -41,12 +46,40 @@ impl Incremental for NumHoles {
     }
 }
 
+impl Batch for NumHoles {
+    fn batch(points: &[Point], reg_idx: usize, _: i32, _: &Image<u8>, _: &()) -> Self {
+        let m = RegionMask::from_points(points, 1);
+        let (mut c1, mut c3, mut cd) = (0, 0, 0);
+
+        for x in 0..(m.width() as i32 - 1) {
+            for y in 0..(m.height() as i32 - 1) {
+                let b = |dx: i32, dy: i32| if m.get(x + dx, y + dy) { 1 } else { 0 };
+                let (bc1, bc3, bcd) = classify_block(b(0, 0), b(1, 0), b(0, 1), b(1, 1));
+                c1 += bc1;
+                c3 += bc3;
+                cd += bcd;
+            }
+        }
+
+        NumHoles {
+            genus: 0.25f32 * ((c1 - c3 + 2 * cd) as f32),
+            reg_idx: reg_idx
+        }
+    }
+}
+
 impl Feature for NumHoles {
     fn value(&self, out: &mut Vec<f32>) {
         out.push(1.0f32 - self.genus);
     }
 }
 
+impl Columns for NumHoles {
+    fn columns(out: &mut Vec<Column>) {
+        out.push(Column::new("num_holes", None, None));
+    }
+}
+
 fn fill_mat(p: Point, m: &mut Matrix, reg_image: &Image<Option<usize>>, self_reg_idx: usize) {
     for x in [-1, 0, 1].iter() {
         for y in [-1, 0, 1].iter() {
@@ -66,23 +99,90 @@ fn count_patterns(m: &Matrix) -> (i32, i32, i32) {
 
     for x in 0..2 {
         for y in 0..2 {
-            let count = m[y][x] + m[y + 1][x] + m[y][x + 1] + m[y + 1][x + 1];
-            match count {
-                1 => c1 += 1,
-                3 => c3 += 1,
-                2 => {
-                    if (m[y + 1][x + 1] + m[y][x] == 2) || (m[y + 1][x] + m[y][x + 1] == 2) {
-                        cd += 1
-                    }
-                },
-                _ => {}
-            }
+            let (bc1, bc3, bcd) = classify_block(m[y][x], m[y][x + 1], m[y + 1][x], m[y + 1][x + 1]);
+            c1 += bc1;
+            c3 += bc3;
+            cd += bcd;
         }
     }
 
     (c1, c3, cd)
 }
 
+/// Classifies 2x2 block of pixels as one of the patterns used to compute
+/// euler number: single pixel, three pixels or two diagonal pixels.
+fn classify_block(tl: i32, tr: i32, bl: i32, br: i32) -> (i32, i32, i32) {
+    match tl + tr + bl + br {
+        1 => (1, 0, 0),
+        3 => (0, 1, 0),
+        2 if tl + br == 2 || tr + bl == 2 => (0, 0, 1),
+        _ => (0, 0, 0)
+    }
+}
+
+#[cfg(test)]
+fn grow(rows: &[&str]) -> (NumHoles, Vec<Point>) {
+    let mut points = vec![];
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if c == 'x' {
+                points.push(Point { x: x as i32, y: y as i32 });
+            }
+        }
+    }
+
+    let img: Image<u8> = Image::from_data(vec![0; 16], 4, 4);
+    let mut reg_image: Image<Option<usize>> = Image::from_data(vec![None; 16], 4, 4);
+    let mut h: NumHoles = Incremental::init(points[0], 0, 0, &());
+    reg_image.set_pixel(points[0].x, points[0].y, Some(0));
+    for p in &points[1..] {
+        h.increment(*p, 0, &img, &reg_image);
+        reg_image.set_pixel(p.x, p.y, Some(0));
+    }
+    (h, points)
+}
+
+#[cfg(test)]
+fn num_holes(h: &NumHoles) -> f32 {
+    let mut v = vec![];
+    h.value(&mut v);
+    v[0]
+}
+
+#[test]
+fn single_pixel_has_no_holes() {
+    let (h, points) = grow(&["x"]);
+    let img: Image<u8> = Image::from_data(vec![0; 16], 4, 4);
+
+    assert_eq!(num_holes(&h), 0.0f32);
+    assert_eq!(num_holes(&NumHoles::batch(&points, 0, 0, &img, &())), 0.0f32);
+}
+
+#[test]
+fn block_has_no_holes() {
+    let (h, points) = grow(&[
+        "xx",
+        "xx",
+    ]);
+    let img: Image<u8> = Image::from_data(vec![0; 16], 4, 4);
+
+    assert_eq!(num_holes(&h), 0.0f32);
+    assert_eq!(num_holes(&NumHoles::batch(&points, 0, 0, &img, &())), 0.0f32);
+}
+
+#[test]
+fn ring_has_one_hole() {
+    let (h, points) = grow(&[
+        "xxx",
+        "x.x",
+        "xxx",
+    ]);
+    let img: Image<u8> = Image::from_data(vec![0; 16], 4, 4);
+
+    assert_eq!(num_holes(&h), 1.0f32);
+    assert_eq!(num_holes(&NumHoles::batch(&points, 0, 0, &img, &())), 1.0f32);
+}
+
 #[test]
 fn count_patterns_test_1() {
 
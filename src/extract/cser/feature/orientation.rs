@@ -0,0 +1,146 @@
+use std::f32::consts::PI;
+
+use image::Image;
+use structures::Point;
+use extract::cser::{Feature, Incremental};
+use extract::cser::feature::Batch;
+use extract::cser::feature::schema::{Column, Columns};
+
+/// Orientation of a region estimated from its second order central moments.
+///
+/// `angle` is the angle of the principal axis in radians, in `(-pi/2, pi/2]`
+/// range, measured from the x axis towards the y axis (i.e. clockwise in the
+/// image). `skew` is the horizontal shift per row, `mu11 / mu02`, which is
+/// zero for upright glyphs and grows with their slant.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Orientation {
+    n: i64,
+    sx: i64,
+    sy: i64,
+    sxx: i64,
+    syy: i64,
+    sxy: i64
+}
+
+impl Orientation {
+    fn add(&mut self, p: Point) {
+        let (x, y) = (p.x as i64, p.y as i64);
+        self.n += 1;
+        self.sx += x;
+        self.sy += y;
+        self.sxx += x * x;
+        self.syy += y * y;
+        self.sxy += x * y;
+    }
+
+    /// Central moments `(mu20, mu02, mu11)` normalized by area.
+    fn central_moments(&self) -> (f32, f32, f32) {
+        let n = self.n as f64;
+        let (mx, my) = (self.sx as f64 / n, self.sy as f64 / n);
+        let mu20 = self.sxx as f64 / n - mx * mx;
+        let mu02 = self.syy as f64 / n - my * my;
+        let mu11 = self.sxy as f64 / n - mx * my;
+        (mu20 as f32, mu02 as f32, mu11 as f32)
+    }
+
+    pub fn angle(&self) -> f32 {
+        let (mu20, mu02, mu11) = self.central_moments();
+        let a = 0.5f32 * (2.0f32 * mu11).atan2(mu20 - mu02);
+        if a <= -PI / 2.0f32 { a + PI } else { a }
+    }
+
+    pub fn skew(&self) -> f32 {
+        let (_, mu02, mu11) = self.central_moments();
+        if mu02 > 0.0f32 { mu11 / mu02 } else { 0.0f32 }
+    }
+}
+
+impl Incremental for Orientation {
+    type Params = ();
+
+    fn init(p: Point, _: usize, _: i32, _: &()) -> Self {
+        let mut o = Orientation { n: 0, sx: 0, sy: 0, sxx: 0, syy: 0, sxy: 0 };
+        o.add(p);
+        o
+    }
+
+    fn increment(&mut self, p: Point, _: i32, _: &Image<u8>, _: &Image<Option<usize>>) {
+        self.add(p);
+    }
+
+    fn merge(&mut self, other: &Self, _: i32, _: &Image<u8>, _: &Image<Option<usize>>) {
+        self.n += other.n;
+        self.sx += other.sx;
+        self.sy += other.sy;
+        self.sxx += other.sxx;
+        self.syy += other.syy;
+        self.sxy += other.sxy;
+    }
+}
+
+impl Batch for Orientation {
+    fn batch(points: &[Point], _: usize, _: i32, _: &Image<u8>, _: &()) -> Self {
+        let mut o = Orientation { n: 0, sx: 0, sy: 0, sxx: 0, syy: 0, sxy: 0 };
+        for p in points {
+            o.add(*p);
+        }
+        o
+    }
+}
+
+impl Feature for Orientation {
+    fn value(&self, out: &mut Vec<f32>) {
+        out.push(self.angle());
+        out.push(self.skew());
+    }
+}
+
+impl Columns for Orientation {
+    fn columns(out: &mut Vec<Column>) {
+        out.push(Column::new("orientation_angle", Some(-PI / 2.0f32), Some(PI / 2.0f32)));
+        out.push(Column::new("orientation_skew", None, None));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f32::consts::PI;
+
+    use image::Image;
+    use structures::Point;
+    use extract::cser::feature::Batch;
+    use super::Orientation;
+
+    fn orientation(points: &[(i32, i32)]) -> Orientation {
+        let img: Image<u8> = Image::from_data(vec![], 0, 0);
+        let points: Vec<Point> = points.iter().map(|p| Point { x: p.0, y: p.1 }).collect();
+        Orientation::batch(&points, 0, 0, &img, &())
+    }
+
+    #[test]
+    fn horizontal_bar() {
+        let o = orientation(&[(0, 0), (1, 0), (2, 0), (3, 0)]);
+        assert_eq!(o.angle(), 0.0f32);
+    }
+
+    #[test]
+    fn vertical_bar() {
+        let o = orientation(&[(0, 0), (0, 1), (0, 2), (0, 3)]);
+        assert_eq!(o.angle(), PI / 2.0f32);
+        assert_eq!(o.skew(), 0.0f32);
+    }
+
+    #[test]
+    fn slanted_bar() {
+        // +--+--+--+
+        // |  |  |xx|
+        // +--------+
+        // |  |xx|  |
+        // +--------+
+        // |xx|  |  |
+        // +--------+
+        let o = orientation(&[(2, 0), (1, 1), (0, 2)]);
+        assert_eq!(o.skew(), -1.0f32);
+        assert!((o.angle() + PI / 4.0f32).abs() < 1e-6f32);
+    }
+}
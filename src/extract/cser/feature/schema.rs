@@ -0,0 +1,104 @@
+/// Description of a single feature vector column.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Column {
+    pub name: String,
+    pub min: Option<f32>,
+    pub max: Option<f32>
+}
+
+impl Column {
+    pub fn new(name: &str, min: Option<f32>, max: Option<f32>) -> Column {
+        Column {
+            name: name.to_string(),
+            min: min,
+            max: max
+        }
+    }
+}
+
+/// Feature that writes the same columns for every region. Columns of
+/// runtime composed features depend on their layout instead, see
+/// `FeatureSet::schema`.
+pub trait Columns {
+    /// Describes the values written by `Feature::value`, in the same order.
+    fn columns(out: &mut Vec<Column>);
+}
+
+/// Names and ranges of the values a feature writes into a feature vector.
+/// Stored together with feature vectors so that consumers can check they
+/// are reading the columns they expect.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Schema {
+    pub columns: Vec<Column>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaMismatch {
+    Length { expected: usize, actual: usize },
+    Column { index: usize, expected: String, actual: String }
+}
+
+impl Schema {
+    pub fn of<F: Columns>() -> Schema {
+        let mut columns = vec![];
+        F::columns(&mut columns);
+        Schema { columns: columns }
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn names<'a>(&'a self) -> Vec<&'a str> {
+        self.columns.iter().map(|c| &c.name[..]).collect()
+    }
+
+    /// Checks that `actual` has the same columns in the same order.
+    pub fn check(&self, actual: &Schema) -> Result<(), SchemaMismatch> {
+        if self.len() != actual.len() {
+            return Err(SchemaMismatch::Length { expected: self.len(), actual: actual.len() });
+        }
+
+        for (i, (e, a)) in self.columns.iter().zip(actual.columns.iter()).enumerate() {
+            if e.name != a.name {
+                return Err(SchemaMismatch::Column {
+                    index: i,
+                    expected: e.name.clone(),
+                    actual: a.name.clone()
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use extract::cser::feature::{AspectRatio, NumHoles, StrokeWidth};
+
+    #[test]
+    fn tuple_schema() {
+        let s = Schema::of::<(AspectRatio, NumHoles, StrokeWidth)>();
+        assert_eq!(s.names(), vec![
+            "aspect_ratio", "num_holes",
+            "stroke_width_mean", "stroke_width_variation", "stroke_width_height_ratio"
+        ]);
+    }
+
+    #[test]
+    fn check_mismatch() {
+        let a = Schema::of::<(AspectRatio, NumHoles)>();
+        let b = Schema::of::<(NumHoles, AspectRatio)>();
+        let c = Schema::of::<AspectRatio>();
+
+        assert_eq!(a.check(&a), Ok(()));
+        assert_eq!(a.check(&b), Err(SchemaMismatch::Column {
+            index: 0,
+            expected: "aspect_ratio".to_string(),
+            actual: "num_holes".to_string()
+        }));
+        assert_eq!(a.check(&c), Err(SchemaMismatch::Length { expected: 2, actual: 1 }));
+    }
+}
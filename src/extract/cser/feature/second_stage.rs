@@ -0,0 +1,176 @@
+use image::Image;
+use image::gradient::sobel_magnitude;
+use structures::Point;
+
+use super::Feature;
+use super::schema::{Column, Columns, Schema};
+use super::feature_set::LayoutError;
+use super::{StrokeWidth, BoundaryGradient, Holes};
+
+/// Per-frame data shared by second-stage features of all candidate regions.
+/// Expensive image transforms are computed once when the frame is created.
+pub struct Frame<'a> {
+    pub image: &'a Image<u8>,
+    pub gradient: Image<f32>
+}
+
+impl<'a> Frame<'a> {
+    pub fn new(image: &'a Image<u8>) -> Frame<'a> {
+        Frame {
+            image: image,
+            gradient: sobel_magnitude(image)
+        }
+    }
+}
+
+/// Feature that is too expensive to be maintained incrementally during
+/// detection and is computed once for final candidate regions from the
+/// full set of region points.
+pub trait SecondStage : Sized {
+    fn compute(points: &[Point], frame: &Frame) -> Self;
+}
+
+/// Feature of a runtime composed `SecondStageSet`, written in second-stage
+/// layouts by its name.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SecondStageKind {
+    StrokeWidth,
+    BoundaryGradient,
+    Holes
+}
+
+impl SecondStageKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            SecondStageKind::StrokeWidth => "stroke_width",
+            SecondStageKind::BoundaryGradient => "boundary_gradient",
+            SecondStageKind::Holes => "holes"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SecondStageKind> {
+        match name {
+            "stroke_width" => Some(SecondStageKind::StrokeWidth),
+            "boundary_gradient" => Some(SecondStageKind::BoundaryGradient),
+            "holes" => Some(SecondStageKind::Holes),
+            _ => None
+        }
+    }
+
+    pub fn columns(&self, out: &mut Vec<Column>) {
+        match *self {
+            SecondStageKind::StrokeWidth => StrokeWidth::columns(out),
+            SecondStageKind::BoundaryGradient => BoundaryGradient::columns(out),
+            SecondStageKind::Holes => Holes::columns(out)
+        }
+    }
+}
+
+/// Parses comma separated list of second-stage feature names, e.g.
+/// `stroke_width,holes`. Unlike detector layouts the list may be empty.
+pub fn parse_second_stage(names: &str) -> Result<Vec<SecondStageKind>, LayoutError> {
+    let mut layout = vec![];
+    for name in names.split(',').map(|n| n.trim()).filter(|n| n.len() > 0) {
+        layout.push(try!(SecondStageKind::from_name(name).ok_or(LayoutError::UnknownFeature(name.to_string()))));
+    }
+    Ok(layout)
+}
+
+#[derive(Debug, Clone)]
+pub enum AnySecondStage {
+    StrokeWidth(StrokeWidth),
+    BoundaryGradient(BoundaryGradient),
+    Holes(Holes)
+}
+
+impl AnySecondStage {
+    pub fn compute(kind: SecondStageKind, points: &[Point], frame: &Frame) -> AnySecondStage {
+        match kind {
+            SecondStageKind::StrokeWidth => AnySecondStage::StrokeWidth(SecondStage::compute(points, frame)),
+            SecondStageKind::BoundaryGradient => AnySecondStage::BoundaryGradient(SecondStage::compute(points, frame)),
+            SecondStageKind::Holes => AnySecondStage::Holes(SecondStage::compute(points, frame))
+        }
+    }
+
+    pub fn value(&self, out: &mut Vec<f32>) {
+        match *self {
+            AnySecondStage::StrokeWidth(ref f) => f.value(out),
+            AnySecondStage::BoundaryGradient(ref f) => f.value(out),
+            AnySecondStage::Holes(ref f) => f.value(out)
+        }
+    }
+}
+
+/// Second-stage features composed at runtime, computed for the points of
+/// final candidates, e.g. peaks of regions.
+#[derive(Debug, Clone)]
+pub struct SecondStageSet {
+    features: Vec<AnySecondStage>
+}
+
+impl SecondStageSet {
+    pub fn compute(layout: &[SecondStageKind], points: &[Point], frame: &Frame) -> SecondStageSet {
+        SecondStageSet {
+            features: layout.iter().map(|kind| AnySecondStage::compute(*kind, points, frame)).collect()
+        }
+    }
+
+    pub fn features<'a>(&'a self) -> &'a [AnySecondStage] {
+        &self.features[..]
+    }
+
+    /// Columns of second-stage sets with `layout`.
+    pub fn schema(layout: &[SecondStageKind]) -> Schema {
+        let mut columns = vec![];
+        for kind in layout {
+            kind.columns(&mut columns);
+        }
+        Schema { columns: columns }
+    }
+}
+
+impl Feature for SecondStageSet {
+    fn value(&self, out: &mut Vec<f32>) {
+        for f in self.features.iter() {
+            f.value(out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use image::Image;
+    use structures::Point;
+    use extract::cser::feature::{Feature, StrokeWidth, BoundaryGradient, Holes, Schema, LayoutError};
+    use super::*;
+
+    #[test]
+    fn parse_second_stage_test() {
+        assert_eq!(parse_second_stage(" stroke_width "), Ok(vec![SecondStageKind::StrokeWidth]));
+        assert_eq!(
+            parse_second_stage("boundary_gradient,stroke_width"),
+            Ok(vec![SecondStageKind::BoundaryGradient, SecondStageKind::StrokeWidth])
+        );
+        assert_eq!(parse_second_stage("holes"), Ok(vec![SecondStageKind::Holes]));
+        assert_eq!(parse_second_stage(""), Ok(vec![]));
+        assert_eq!(parse_second_stage("stroke_width,foo"), Err(LayoutError::UnknownFeature("foo".to_string())));
+    }
+
+    #[test]
+    fn set_matches_features() {
+        let img: Image<u8> = Image::from_data((0..16).map(|i| (i * 16) as u8).collect(), 4, 4);
+        let frame = Frame::new(&img);
+        let points: Vec<Point> = (0..4).map(|y| Point { x: 1, y: y }).collect();
+        let layout = vec![SecondStageKind::StrokeWidth, SecondStageKind::BoundaryGradient, SecondStageKind::Holes];
+
+        let mut actual = vec![];
+        SecondStageSet::compute(&layout, &points, &frame).value(&mut actual);
+        let mut expected = vec![];
+        StrokeWidth::compute(&points, &frame).value(&mut expected);
+        BoundaryGradient::compute(&points, &frame).value(&mut expected);
+        Holes::compute(&points, &frame).value(&mut expected);
+
+        assert_eq!(actual, expected);
+        assert_eq!(SecondStageSet::schema(&layout), Schema::of::<(StrokeWidth, BoundaryGradient, Holes)>());
+    }
+}
@@ -0,0 +1,207 @@
+use std::cmp::min;
+
+use image::Image;
+use structures::Point;
+
+use super::Feature;
+use super::{SecondStage, Frame};
+use super::mask::RegionMask;
+use super::schema::{Column, Columns};
+
+// chamfer 3-4 distance transform weights
+static ORTHO: i32 = 3;
+static DIAG: i32 = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StrokeWidth {
+    mean: f32,
+    variation: f32,
+    height_ratio: f32
+}
+
+impl StrokeWidth {
+    pub fn mean(&self) -> f32 {
+        self.mean
+    }
+
+    /// Coefficient of variation (std / mean) of stroke width.
+    pub fn variation(&self) -> f32 {
+        self.variation
+    }
+
+    /// Mean stroke width relative to region height.
+    pub fn height_ratio(&self) -> f32 {
+        self.height_ratio
+    }
+}
+
+impl SecondStage for StrokeWidth {
+    fn compute(points: &[Point], _: &Frame) -> Self {
+        let m = RegionMask::from_points(points, 1);
+        let dt = distance_transform(&m);
+        let widths = ridge_widths(&m, &dt);
+
+        let height = (m.height() - 2) as f32;
+
+        if widths.len() == 0 {
+            return StrokeWidth { mean: 0.0f32, variation: 0.0f32, height_ratio: 0.0f32 };
+        }
+
+        let n = widths.len() as f32;
+        let mean = widths.iter().fold(0.0f32, |a, w| a + w) / n;
+        let var = widths.iter().fold(0.0f32, |a, w| a + (w - mean) * (w - mean)) / n;
+
+        StrokeWidth {
+            mean: mean,
+            variation: var.sqrt() / mean,
+            height_ratio: mean / height
+        }
+    }
+}
+
+impl Feature for StrokeWidth {
+    fn value(&self, out: &mut Vec<f32>) {
+        out.push(self.mean);
+        out.push(self.variation);
+        out.push(self.height_ratio);
+    }
+}
+
+impl Columns for StrokeWidth {
+    fn columns(out: &mut Vec<Column>) {
+        out.push(Column::new("stroke_width_mean", Some(0.0f32), None));
+        out.push(Column::new("stroke_width_variation", Some(0.0f32), None));
+        out.push(Column::new("stroke_width_height_ratio", Some(0.0f32), None));
+    }
+}
+
+/// Two-pass chamfer 3-4 distance from every region pixel to the closest
+/// background pixel. Background pixels get zero distance.
+fn distance_transform(m: &RegionMask) -> Image<i32> {
+    let (w, h) = (m.width() as i32, m.height() as i32);
+    let inf = 3 * (w + h);
+    let mut dt: Image<i32> = m.mask.map(|v| if *v { inf } else { 0 });
+
+    let forward = [(-1, 0, ORTHO), (0, -1, ORTHO), (-1, -1, DIAG), (1, -1, DIAG)];
+    let backward = [(1, 0, ORTHO), (0, 1, ORTHO), (1, 1, DIAG), (-1, 1, DIAG)];
+
+    for y in 0..h {
+        for x in 0..w {
+            relax(&mut dt, x, y, &forward);
+        }
+    }
+
+    for y in (0..h).rev() {
+        for x in (0..w).rev() {
+            relax(&mut dt, x, y, &backward);
+        }
+    }
+
+    dt
+}
+
+fn relax(dt: &mut Image<i32>, x: i32, y: i32, mask: &[(i32, i32, i32)]) {
+    let mut d = dt[(x, y)];
+    if d == 0 {
+        return;
+    }
+
+    for &(dx, dy, c) in mask {
+        if dt.inside(x + dx, y + dy) {
+            d = min(d, dt[(x + dx, y + dy)] + c);
+        }
+    }
+
+    dt.set_pixel(x, y, d);
+}
+
+/// Whether the ridge at `(x, y)` is a single pixel wide, i.e. both
+/// neighbors along some axis are closer to the background. Otherwise the
+/// ridge is two pixels wide and the stroke width is even.
+fn is_odd_ridge(dt: &Image<i32>, x: i32, y: i32) -> bool {
+    let d = dt[(x, y)];
+    let smaller = |x: i32, y: i32| !dt.inside(x, y) || dt[(x, y)] < d;
+
+    (smaller(x - 1, y) && smaller(x + 1, y)) || (smaller(x, y - 1) && smaller(x, y + 1))
+}
+
+/// Stroke widths sampled at the ridge of the distance transform, i.e. at
+/// region pixels whose distance is not smaller than any of its 8 neighbors.
+fn ridge_widths(m: &RegionMask, dt: &Image<i32>) -> Vec<f32> {
+    let mut res = vec![];
+
+    for y in 0..(m.height() as i32) {
+        for x in 0..(m.width() as i32) {
+            let d = dt[(x, y)];
+            if d == 0 {
+                continue;
+            }
+
+            let mut is_ridge = true;
+            for dx in -1..2 {
+                for dy in -1..2 {
+                    if dt.inside(x + dx, y + dy) && dt[(x + dx, y + dy)] > d {
+                        is_ridge = false;
+                    }
+                }
+            }
+
+            if is_ridge {
+                // the center of an even stroke lies between two ridge pixels
+                let width = 2.0f32 * (d as f32) / (ORTHO as f32);
+                res.push(if is_odd_ridge(dt, x, y) { width - 1.0f32 } else { width });
+            }
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Image;
+    use structures::Point;
+    use extract::cser::feature::{SecondStage, Frame};
+
+    fn bar(x0: i32, width: i32, height: i32) -> Vec<Point> {
+        let mut points = vec![];
+        for x in x0..(x0 + width) {
+            for y in 0..height {
+                points.push(Point { x: x, y: y });
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn constant_width_bar() {
+        let img: Image<u8> = Image::from_data(vec![], 0, 0);
+        let sw = StrokeWidth::compute(&bar(0, 3, 20), &Frame::new(&img));
+
+        assert_eq!(sw.mean(), 3.0f32);
+        assert_eq!(sw.variation(), 0.0f32);
+        assert_eq!(sw.height_ratio(), 3.0f32 / 20.0f32);
+    }
+
+    #[test]
+    fn even_width_bars() {
+        let img: Image<u8> = Image::from_data(vec![], 0, 0);
+
+        for width in [2, 4].iter() {
+            let sw = StrokeWidth::compute(&bar(0, *width, 20), &Frame::new(&img));
+
+            assert_eq!(sw.mean(), *width as f32);
+            assert_eq!(sw.variation(), 0.0f32);
+        }
+    }
+
+    #[test]
+    fn single_pixel() {
+        let img: Image<u8> = Image::from_data(vec![], 0, 0);
+        let sw = StrokeWidth::compute(&[Point { x: 4, y: 4 }], &Frame::new(&img));
+
+        assert_eq!(sw.mean(), 1.0f32);
+        assert_eq!(sw.variation(), 0.0f32);
+    }
+}
@@ -2,7 +2,11 @@ use image::Image;
 use structures::Point;
 
 pub trait Incremental {
-    fn init(p: Point, reg_idx: usize, thres: i32) -> Self;
+    /// Configuration shared by all regions of a single detection, e.g. the
+    /// layout of a `FeatureSet`. `()` for features that don't need any.
+    type Params;
+
+    fn init(p: Point, reg_idx: usize, thres: i32, params: &Self::Params) -> Self;
     fn increment(&mut self, p: Point, thres: i32, _: &Image<u8>,  reg_img: &Image<Option<usize>>);
     fn merge(&mut self, other: &Self, thres: i32, _: &Image<u8>, _: &Image<Option<usize>>);
 }
@@ -2,10 +2,12 @@ mod region;
 mod incremental;
 mod detector;
 mod trace;
+mod verify;
 pub mod feature;
 
 pub use self::detector::detector::CserDetector;
 pub use self::incremental::{Incremental};
-pub use self::region::Region;
+pub use self::region::{Region, RegionParams, Weighting};
 pub use self::trace::{Trace, FullTrace, PrintTrace, EmptyTrace, TracedRegion};
+pub use self::verify::{VerifyTrace, Mismatch};
 pub use self::feature::Feature;
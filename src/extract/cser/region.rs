@@ -1,12 +1,83 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
 use structures::{Point, Rect};
 use image::Image;
+use ml::Classifier;
 use super::feature::Feature;
 use super::incremental::{Incremental};
 use extract::ExtremalRegion;
 
 
+/// Weight has to rise and then drop at least this much around a peak.
 static PEAK_THRESHOLD: f32 = 0.05f32;
 
+/// Weight gain that replaces the best state of a rising region, so that
+/// features aren't cloned on every pixel.
+static PEAK_STEP: f32 = 0.01f32;
+
+/// Parameters of regions created by the detector: parameters of their
+/// features and the weighting of regions, if any.
+#[derive(Debug, Clone)]
+pub struct RegionParams<P> {
+    pub features: P,
+    pub weighting: Option<Rc<Weighting>>
+}
+
+impl<P> RegionParams<P> {
+    /// Regions that aren't weighted: their weight stays -1 and they have
+    /// no peaks.
+    pub fn new(features: P) -> RegionParams<P> {
+        RegionParams { features: features, weighting: None }
+    }
+
+    /// Regions weighted by the probability that `classifier` gives them of
+    /// being a character of any kind, so plate border blobs of multi-class
+    /// classifiers weigh little.
+    pub fn weighted_by(mut self, classifier: Rc<Classifier>) -> RegionParams<P> {
+        self.weighting = Some(Rc::new(Weighting::new(classifier)));
+        self
+    }
+}
+
+/// Classifier scoring regions, with buffers shared by all regions of the
+/// image, so that rescoring a region on every pixel doesn't allocate.
+pub struct Weighting {
+    classifier: Rc<Classifier>,
+    features: RefCell<Vec<f32>>,
+    probabilities: RefCell<Vec<f32>>,
+    scratch: RefCell<Vec<f32>>
+}
+
+impl Weighting {
+    pub fn new(classifier: Rc<Classifier>) -> Weighting {
+        Weighting {
+            classifier: classifier,
+            features: RefCell::new(vec![]),
+            probabilities: RefCell::new(vec![]),
+            scratch: RefCell::new(vec![])
+        }
+    }
+
+    pub fn classifier(&self) -> &Rc<Classifier> {
+        &self.classifier
+    }
+
+    fn score<A: Feature>(&self, features: &A) -> f32 {
+        let mut x = self.features.borrow_mut();
+        x.clear();
+        features.value(&mut x);
+        self.classifier.score_with(&x, &mut self.probabilities.borrow_mut(), &mut self.scratch.borrow_mut())
+    }
+}
+
+impl fmt::Debug for Weighting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Weighting({} classes)", self.classifier.num_classes())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Region<A: Incremental + Feature + Clone> {
     features: A,
@@ -14,26 +85,73 @@ pub struct Region<A: Incremental + Feature + Clone> {
     points: Vec<Point>,
     weight: f32,
     peaks: Vec<(Rect, A)>,
-    prev_weight: f32,
+    /// Ranges of `points` of every peak.
+    peak_ranges: Vec<(usize, usize)>,
+    weighting: Option<Rc<Weighting>>,
+    /// Best state since the weight rose from `low`, with its weight and
+    /// number of its points.
+    best: Option<(f32, Rect, A, usize)>,
+    /// Lowest weight since the last peak.
+    low: f32,
     threshold: i32
 }
 
 impl<A: Incremental + Feature + Clone> Region<A> {
-    fn is_peak(&self, new_weight: f32) -> bool {
-        self.weight - self.prev_weight > PEAK_THRESHOLD &&
-        self.weight - new_weight > PEAK_THRESHOLD
+    /// Rescores the region after it grew. A peak is the best state of the
+    /// region between a rise and a drop of the weight, both larger than
+    /// `PEAK_THRESHOLD`, so slow declines are peaks too.
+    fn update_weight(&mut self, thres: i32) {
+        self.threshold = thres;
+        let new_weight = match self.weighting {
+            Some(ref w) => w.score(&self.features),
+            None => return
+        };
+        let best = self.best.as_ref().map(|b| b.0);
+
+        match best {
+            Some(w) if w - new_weight > PEAK_THRESHOLD => {
+                let (_, bounds, features, len) = self.best.take().unwrap();
+                self.peaks.push((bounds, features));
+                self.peak_ranges.push((0, len));
+                self.low = new_weight;
+            },
+            _ => {
+                self.low = self.low.min(new_weight);
+                let better = match best {
+                    Some(w) => new_weight > w + PEAK_STEP,
+                    None => new_weight - self.low > PEAK_THRESHOLD
+                };
+                if better {
+                    self.best = Some((new_weight, self.bounds, self.features.clone(), self.points.len()));
+                }
+            }
+        }
+
+        self.weight = new_weight;
+    }
+
+    /// Points of the region at its `peak`-th peak. Points are only ever
+    /// appended, so they are a range of the current points.
+    pub fn peak_points<'a>(&'a self, peak: usize) -> &'a [Point] {
+        let (start, end) = self.peak_ranges[peak];
+        &self.points[start..end]
     }
 }
 
 impl<A: Incremental + Feature + Clone> Incremental for Region<A> {
-    fn init(p: Point, reg_idx: usize, thres: i32) -> Self {
+    type Params = RegionParams<A::Params>;
+
+    fn init(p: Point, reg_idx: usize, thres: i32, params: &RegionParams<A::Params>) -> Self {
         Region {
-            features: A::init(p, reg_idx, thres),
+            features: A::init(p, reg_idx, thres, &params.features),
             bounds: Rect(p, p),
             points: vec![p],
             weight: -1f32,
             peaks: vec![],
-            prev_weight: -1f32,
+            peak_ranges: vec![],
+            weighting: params.weighting.clone(),
+            best: None,
+            low: 1.0f32 / 0.0f32,
             threshold: thres
         }
     }
@@ -41,34 +159,26 @@ impl<A: Incremental + Feature + Clone> Incremental for Region<A> {
     fn increment(&mut self, p: Point, thres: i32, img: &Image<u8>,  reg_img: &Image<Option<usize>>) {
         self.features.increment(p, thres, img, reg_img);
         self.bounds = self.bounds.expand(Rect(p, p));
-        self.weight += 0.1;
         self.points.push(p);
 
-        let new_weight = ((self.points.len() % 50) as f32) / 50.0f32;
-
-        if self.is_peak(new_weight) {
-            self.peaks.push((self.bounds, self.features.clone()));
-        }
-
-        self.prev_weight = self.weight;
-        self.weight = new_weight;
-        self.threshold = thres;
+        self.update_weight(thres);
     }
 
     fn merge(&mut self, r: &Self, thres: i32, img: &Image<u8>, reg_image: &Image<Option<usize>>) {
+        let offset = self.points.len();
         self.bounds = self.bounds.expand(r.bounds);
         self.points.extend_from_slice(&r.points[..]);
         self.features.merge(&r.features, thres, img, reg_image);
 
-        let new_weight = ((self.points.len() % 50) as f32) / 50.0f32;
+        self.update_weight(thres);
 
-        if self.is_peak(new_weight) {
-            self.peaks.push((self.bounds, self.features.clone()));
+        // `r` ends here, so its weight drops to the one of the merged region
+        if let Some(ref best) = r.best {
+            if best.0 - self.weight > PEAK_THRESHOLD {
+                self.peaks.push((best.1, best.2.clone()));
+                self.peak_ranges.push((offset, offset + best.3));
+            }
         }
-
-        self.prev_weight = self.weight;
-        self.weight = new_weight;
-        self.threshold = thres;
     }
 }
 
@@ -84,7 +194,7 @@ impl<A: Incremental + Feature + Clone> ExtremalRegion for Region<A> {
     }
 
     fn weight(&self) -> f32 {
-        ((self.points.len() % 10) as f32) / 10.0f32
+        self.weight
     }
 
     fn bounds(&self) -> Rect {
@@ -116,7 +226,9 @@ mod test {
     }
 
     impl Incremental for FakeFeature {
-        fn init(p: Point, _: usize, _: i32) -> Self {
+        type Params = ();
+
+        fn init(p: Point, _: usize, _: i32, _: &()) -> Self {
             FakeFeature { init_point: p, incremented: 0, merged: 0 }
         }
 
@@ -136,7 +248,7 @@ mod test {
     describe! region {
         describe! init {
             before_each {
-                let region: Region<FakeFeature> = Incremental::init(Point { x: 6, y: 3 }, 0, 0);
+                let region: Region<FakeFeature> = Incremental::init(Point { x: 6, y: 3 }, 0, 0, &RegionParams::new(()));
             }
 
             it "should create Region with one point bounds" {
@@ -158,7 +270,7 @@ mod test {
                 let img: Image<u8> = Image::from_data(vec![], 0, 0);
                 let reg_img: Image<Option<usize>> = Image::from_data(vec![], 0, 0);
 
-                let mut region: Region<FakeFeature> = Incremental::init(Point { x: 6, y: 3 }, 0, 0);
+                let mut region: Region<FakeFeature> = Incremental::init(Point { x: 6, y: 3 }, 0, 0, &RegionParams::new(()));
                 region.increment(Point { x: 6, y: 4 }, 0, &img, &reg_img);
             }
 
@@ -181,6 +293,11 @@ mod test {
             it "should increment features" {
                 assert_eq!(region.features.incremented, 1);
             }
+
+            it "should keep weight of unweighted region" {
+                assert_eq!(region.weight, -1f32);
+                assert_eq!(region.peaks.len(), 0);
+            }
         }
 
         describe! merge {
@@ -190,11 +307,11 @@ mod test {
 
                 let r1p1 = Point { x: 6, y: 3 };
                 let r1p2 = Point { x: 6, y: 4 };
-                let mut r1: Region<FakeFeature> = Incremental::init(r1p1, 0, 0);
+                let mut r1: Region<FakeFeature> = Incremental::init(r1p1, 0, 0, &RegionParams::new(()));
                 r1.increment(r1p2, 0, &img, &reg_img);
 
                 let r2p = Point { x:7, y: 3 };
-                let r2: Region<FakeFeature> = Incremental::init(r2p, 1, 0);
+                let r2: Region<FakeFeature> = Incremental::init(r2p, 1, 0, &RegionParams::new(()));
 
                 r1.merge(&r2, 0, &img, &reg_img);
             }
@@ -13,7 +13,9 @@ use image::Image;
 use image::pixel::{ToLuma, ToRgba, Rgba};
 use structures::{Point, Rect};
 use extract::ExtremalRegion;
+use ml::Explanation;
 use super::{Incremental};
+use super::feature::Schema;
 
 static MAX_THRES_REGS: i32 = 1000000;
 
@@ -84,13 +86,23 @@ pub type TraceWriteResult<T> = Result<T, TraceWriteError>;
 
 #[derive(Debug, RustcEncodable, RustcDecodable)]
 pub struct TraceResult {
+    schema: Schema,
     regions: HashMap<i32, RegionSnapshot>,
     regions_map: Vec<Vec<Vec<i32>>>,
 }
 
+impl TraceResult {
+    pub fn schema<'a>(&'a self) -> &'a Schema {
+        &self.schema
+    }
+}
+
 impl<'a, R: ExtremalRegion + Clone> FullTrace<'a, R> {
+    /// Creates trace for regions with features described by `schema`,
+    /// e.g. `Schema::of::<R::F>()` or `FeatureSet::schema(&layout)`.
     pub fn new(
         path: &'a str,
+        schema: Schema,
         image_width: usize,
         image_height: usize,
         min_region_dims: (i32, i32),
@@ -99,6 +111,7 @@ impl<'a, R: ExtremalRegion + Clone> FullTrace<'a, R> {
         FullTrace {
             path: path,
             trace_result: TraceResult {
+                schema: schema,
                 regions: HashMap::new(),
                 regions_map: vec![vec![vec![];image_width]; image_height],
             },
@@ -119,6 +132,15 @@ impl<'a, R: ExtremalRegion + Clone> FullTrace<'a, R> {
         &self.trace_result
     }
 
+    /// Adds explanation of the score of every traced region by its
+    /// feature vector, e.g. `trace.explain(|x| ml::explain(&model, x))`
+    /// with the model regions were weighted by.
+    pub fn explain<F: Fn(&[f32]) -> Explanation>(&mut self, explain: F) {
+        for r in self.trace_result.regions.values_mut() {
+            r.explanation = Some(explain(&r.features));
+        }
+    }
+
     pub fn as_json(&self) -> TraceWriteResult<String> {
         json::encode(&self.trace_result)
             .map_err(|e| TraceWriteError::EncodeError(e))
@@ -156,7 +178,8 @@ impl<'a, R: ExtremalRegion + Clone> Trace<TracedRegion<R>> for FullTrace<'a, R>
                     let rs = RegionSnapshot {
                         features: f,
                         bounds: r.bounds(),
-                        thres: r.threshold()
+                        thres: r.threshold(),
+                        explanation: None
                     };
                     (r.threshold() * MAX_THRES_REGS + (i as i32), rs)
                 });
@@ -192,7 +215,8 @@ pub struct TracedRegion<R: ExtremalRegion + Clone> {
 pub struct RegionSnapshot {
     features: Vec<f32>,
     bounds: Rect,
-    thres: i32
+    thres: i32,
+    explanation: Option<Explanation>
 }
 
 impl<R: ExtremalRegion + Clone> ExtremalRegion for TracedRegion<R> {
@@ -224,9 +248,11 @@ impl<R: ExtremalRegion + Clone> ExtremalRegion for TracedRegion<R> {
 }
 
 impl<R: ExtremalRegion + Incremental + Clone> Incremental for TracedRegion<R> {
-    fn init(p: Point, reg_idx: usize, thres: i32) -> Self {
+    type Params = R::Params;
+
+    fn init(p: Point, reg_idx: usize, thres: i32, params: &R::Params) -> Self {
         TracedRegion {
-            region: Incremental::init(p, reg_idx, thres)
+            region: Incremental::init(p, reg_idx, thres, params)
         }
     }
 
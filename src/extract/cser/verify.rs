@@ -0,0 +1,78 @@
+use image::Image;
+use extract::ExtremalRegion;
+use super::{Trace, Incremental};
+use super::feature::{Feature, Batch};
+
+static TOLERANCE: f32 = 1e-4f32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub step: i32,
+    pub region: usize,
+    pub incremental: Vec<f32>,
+    pub batch: Vec<f32>
+}
+
+/// Trace that checks incremental features of every live region against
+/// their batch reference implementation after each detection step.
+/// Very slow, intended for tests and debugging new features. `params` are
+/// the ones the detector creates features with.
+pub struct VerifyTrace<'a, P> {
+    img: &'a Image<u8>,
+    params: P,
+    mismatches: Vec<Mismatch>
+}
+
+impl<'a, P> VerifyTrace<'a, P> {
+    pub fn new(img: &'a Image<u8>, params: P) -> VerifyTrace<'a, P> {
+        VerifyTrace {
+            img: img,
+            params: params,
+            mismatches: vec![]
+        }
+    }
+
+    pub fn mismatches<'b>(&'b self) -> &'b [Mismatch] {
+        &self.mismatches[..]
+    }
+}
+
+impl<'a, P, R: ExtremalRegion> Trace<R> for VerifyTrace<'a, P> where R::F: Batch + Incremental<Params = P> {
+    fn step(&mut self, num: i32, all_regions: &[R], reg_img: &Image<Option<usize>>) {
+        let mut incremental = vec![];
+        let mut batch = vec![];
+
+        for (i, r) in all_regions.iter().enumerate() {
+            // regions that were merged into other regions are not
+            // updated anymore, their points belong to the new region
+            let p = r.points()[0];
+            if reg_img[(p.x, p.y)] != Some(i) {
+                continue;
+            }
+
+            incremental.clear();
+            batch.clear();
+
+            r.feature_vec(&mut incremental);
+            <R::F as Batch>::batch(r.points(), i, r.threshold(), self.img, &self.params).value(&mut batch);
+
+            if !same(&incremental, &batch) {
+                self.mismatches.push(Mismatch {
+                    step: num,
+                    region: i,
+                    incremental: incremental.clone(),
+                    batch: batch.clone()
+                });
+            }
+        }
+    }
+
+    fn result(&self, _: &[R], _: &Image<Option<usize>>) {}
+}
+
+fn same(a: &[f32], b: &[f32]) -> bool {
+    a.len() == b.len() &&
+    a.iter().zip(b.iter()).all(|(x, y)| {
+        (x == y) || (x - y).abs() <= TOLERANCE * x.abs().max(1.0f32)
+    })
+}
@@ -1,10 +1,569 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+
 use image::Image;
-use extract::structures::NumberPlate;
+use structures::{Point, Quad};
+use ml::{Classifier, Explanation};
+use ml::explain::explain_by_occlusion;
+use extract::{ExtremalRegion, RegionDetector};
+use extract::cser::{Region, RegionParams, CserDetector, EmptyTrace};
+use extract::cser::feature::{Feature, FeatureSet, FeatureKind, Frame, SecondStageKind, SecondStageSet};
+use extract::structures::{NumberPlate, Symbol};
+
+type Detector = CserDetector<Region<FeatureSet>, EmptyTrace>;
 
 pub trait ExtractPlate {
-    fn extract<T>(_: &Image<u8>) -> Vec<NumberPlate> {
-        return vec![];
+    /// Plates found in `img`, the most probable first.
+    fn extract(&self, img: &Image<u8>) -> Vec<NumberPlate>;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GroupingParams {
+    /// Candidates scored less are not characters.
+    pub min_score: f32,
+    /// Neighbouring symbols differ in height at most this many times.
+    pub max_height_ratio: f32,
+    /// Vertical offset of centers of neighbouring symbols, relative to
+    /// their mean height.
+    pub max_vertical_offset: f32,
+    /// Horizontal gap between neighbouring symbols, relative to their
+    /// mean height.
+    pub max_gap: f32,
+    /// Coefficient of variation of distances between centers of
+    /// neighbouring symbols of a line.
+    pub max_spacing_cv: f32,
+    pub min_symbols: usize,
+    pub max_symbols: usize,
+    /// Of candidates covering this much of the smaller one only the best
+    /// scored is kept, detector finds the same character at many thresholds.
+    pub max_overlap: f32
+}
+
+impl Default for GroupingParams {
+    fn default() -> GroupingParams {
+        GroupingParams {
+            min_score: 0.5f32,
+            max_height_ratio: 1.5f32,
+            max_vertical_offset: 0.35f32,
+            max_gap: 1.2f32,
+            max_spacing_cv: 0.35f32,
+            min_symbols: 3,
+            max_symbols: 10,
+            max_overlap: 0.6f32
+        }
+    }
+}
+
+fn mean(xs: &[f32]) -> f32 {
+    if xs.len() == 0 { 0.0f32 } else { xs.iter().fold(0.0f32, |a, x| a + x) / (xs.len() as f32) }
+}
+
+/// Coefficient of variation, zero for fewer than two values.
+fn cv(xs: &[f32]) -> f32 {
+    let m = mean(xs);
+    if xs.len() < 2 || m == 0.0f32 {
+        return 0.0f32;
+    }
+    let var = xs.iter().fold(0.0f32, |a, x| a + (x - m) * (x - m)) / (xs.len() as f32);
+    var.sqrt() / m.abs()
+}
+
+fn center(s: &Symbol) -> (f32, f32) {
+    let b = s.bound();
+    (((b.0.x + b.1.x) as f32) * 0.5f32, ((b.0.y + b.1.y) as f32) * 0.5f32)
+}
+
+/// Distances between centers of neighbouring symbols.
+fn pitches(line: &[Symbol]) -> Vec<f32> {
+    line.windows(2).map(|w| center(&w[1]).0 - center(&w[0]).0).collect()
+}
+
+/// Least squares line `y = a + b * x` through symbol centers.
+fn fit_line(line: &[Symbol]) -> (f32, f32) {
+    let centers: Vec<(f32, f32)> = line.iter().map(center).collect();
+    let xs: Vec<f32> = centers.iter().map(|c| c.0).collect();
+    let ys: Vec<f32> = centers.iter().map(|c| c.1).collect();
+    let (mx, my) = (mean(&xs), mean(&ys));
+
+    let sxx = xs.iter().fold(0.0f32, |a, x| a + (x - mx) * (x - mx));
+    let sxy = centers.iter().fold(0.0f32, |a, c| a + (c.0 - mx) * (c.1 - my));
+    let b = if sxx > 0.0f32 { sxy / sxx } else { 0.0f32 };
+    (my - b * mx, b)
+}
+
+/// Names of `plate_features`.
+pub static PLATE_FEATURES: [&'static str; 6] = [
+    "num_symbols", "mean_symbol_score", "height_variation",
+    "spacing_variation", "line_offset", "aspect_ratio"
+];
+
+/// Indices of `plate_features`.
+pub static NUM_SYMBOLS: usize = 0;
+pub static MEAN_SYMBOL_SCORE: usize = 1;
+pub static HEIGHT_VARIATION: usize = 2;
+pub static SPACING_VARIATION: usize = 3;
+pub static LINE_OFFSET: usize = 4;
+pub static LINE_ASPECT_RATIO: usize = 5;
+
+/// Features of a line of symbols for plate scorers: number of symbols,
+/// mean symbol score, variation of heights, variation of spacing, mean
+/// distance of centers from the fitted line relative to the mean height
+/// and aspect ratio of the line.
+pub fn plate_features(line: &[Symbol]) -> Vec<f32> {
+    let heights: Vec<f32> = line.iter().map(|s| s.bound().height() as f32).collect();
+    let scores: Vec<f32> = line.iter().map(|s| s.score()).collect();
+    let mean_height = mean(&heights);
+
+    let (a, b) = fit_line(line);
+    let residuals: Vec<f32> = line.iter().map(|s| { let c = center(s); (c.1 - a - b * c.0).abs() }).collect();
+
+    let width = line.last().map(|s| s.bound().1.x).unwrap_or(0) - line.first().map(|s| s.bound().0.x).unwrap_or(0) + 1;
+
+    let mut f = vec![0.0f32; PLATE_FEATURES.len()];
+    f[NUM_SYMBOLS] = line.len() as f32;
+    f[MEAN_SYMBOL_SCORE] = mean(&scores);
+    f[HEIGHT_VARIATION] = cv(&heights);
+    f[SPACING_VARIATION] = cv(&pitches(line));
+    if mean_height > 0.0f32 {
+        f[LINE_OFFSET] = mean(&residuals) / mean_height;
+        f[LINE_ASPECT_RATIO] = (width as f32) / mean_height;
+    }
+    f
+}
+
+/// Groups character candidates into horizontal lines of symbols of
+/// similar height, aligned vertically and regularly spaced.
+pub struct PlateGrouper {
+    pub params: GroupingParams,
+    scorer: Option<Rc<Classifier>>
+}
+
+impl Default for PlateGrouper {
+    fn default() -> PlateGrouper {
+        PlateGrouper::new(GroupingParams::default())
+    }
+}
+
+impl PlateGrouper {
+    pub fn new(params: GroupingParams) -> PlateGrouper {
+        PlateGrouper { params: params, scorer: None }
+    }
+
+    /// Plates get the score of `scorer` applied to `plate_features` of
+    /// their symbols instead of the mean score of symbols weighted by the
+    /// regularity of the line.
+    pub fn with_scorer(mut self, scorer: Rc<Classifier>) -> PlateGrouper {
+        self.scorer = Some(scorer);
+        self
+    }
+
+    /// Drops candidates scored too low and duplicates of better scored
+    /// candidates.
+    fn suppress(&self, candidates: &[Symbol]) -> Vec<Symbol> {
+        let mut sorted: Vec<Symbol> = candidates.iter().cloned().filter(|s| s.score() >= self.params.min_score).collect();
+        sorted.sort_by(|a, b| b.score().partial_cmp(&a.score()).unwrap_or(Ordering::Equal));
+
+        let mut kept: Vec<Symbol> = vec![];
+        for s in sorted {
+            let duplicate = kept.iter().any(|k| {
+                let smaller = if k.bound().area() < s.bound().area() { k.bound().area() } else { s.bound().area() };
+                let common = k.bound().intersect(s.bound()).map(|i| i.area()).unwrap_or(0);
+                (common as f32) > self.params.max_overlap * (smaller as f32)
+            });
+            if !duplicate {
+                kept.push(s);
+            }
+        }
+        kept
+    }
+
+    /// Horizontal gap from `a` to `b` if `b` can follow `a` in a line.
+    fn gap(&self, a: &Symbol, b: &Symbol) -> Option<f32> {
+        let (ha, hb) = (a.bound().height() as f32, b.bound().height() as f32);
+        let mean_height = (ha + hb) * 0.5f32;
+        let gap = (b.bound().0.x - a.bound().1.x - 1) as f32;
+
+        let similar = ha.max(hb) <= self.params.max_height_ratio * ha.min(hb);
+        let aligned = (center(a).1 - center(b).1).abs() <= self.params.max_vertical_offset * mean_height;
+        // symbols may touch, but `b` must be on the right of `a`
+        let close = gap <= self.params.max_gap * mean_height && center(b).0 > a.bound().1.x as f32;
+
+        if similar && aligned && close { Some(gap) } else { None }
+    }
+
+    /// Chains of symbols. Links between symbols and their compatible
+    /// neighbours on the right are taken the shortest first, every symbol
+    /// has at most one neighbour on each side.
+    fn chains(&self, symbols: &[Symbol]) -> Vec<Vec<Symbol>> {
+        let n = symbols.len();
+        let mut links = vec![];
+        for i in 0..n {
+            for j in 0..n {
+                if let Some(g) = self.gap(&symbols[i], &symbols[j]) {
+                    links.push((i, j, g));
+                }
+            }
+        }
+        links.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal));
+
+        let mut next: Vec<Option<usize>> = vec![None; n];
+        let mut prev: Vec<Option<usize>> = vec![None; n];
+        for &(i, j, _) in links.iter() {
+            if next[i].is_none() && prev[j].is_none() {
+                next[i] = Some(j);
+                prev[j] = Some(i);
+            }
+        }
+
+        let mut res = vec![];
+        for start in (0..n).filter(|i| prev[*i].is_none()) {
+            let mut chain = vec![symbols[start]];
+            let mut i = start;
+            while let Some(j) = next[i] {
+                chain.push(symbols[j]);
+                i = j;
+            }
+            res.push(chain);
+        }
+        res
+    }
+
+    /// Splits line at the most irregular spacing until spacing of every
+    /// part is regular enough.
+    fn split_irregular(&self, mut line: Vec<Symbol>, out: &mut Vec<Vec<Symbol>>) {
+        if line.len() < self.params.min_symbols {
+            return;
+        }
+
+        let p = pitches(&line);
+        if line.len() <= self.params.max_symbols && cv(&p) <= self.params.max_spacing_cv {
+            out.push(line);
+            return;
+        }
+
+        let mut sorted = p.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let median = sorted[sorted.len() / 2];
+
+        let k = (0..p.len())
+            .fold(0, |best, i| if (p[i] - median).abs() > (p[best] - median).abs() { i } else { best });
+
+        let right = line.split_off(k + 1);
+        self.split_irregular(line, out);
+        self.split_irregular(right, out);
+    }
+
+    fn score(&self, features: &[f32]) -> f32 {
+        match self.scorer {
+            Some(ref scorer) => scorer.score(features),
+            None => features[MEAN_SYMBOL_SCORE] / (1.0f32 + features[HEIGHT_VARIATION] + features[SPACING_VARIATION])
+        }
+    }
+
+    /// Explains the score of a plate of `line` symbols. Every plate feature
+    /// contributes the change of the score when it's replaced by its value
+    /// for a perfectly regular line of symbols scored 1.
+    pub fn explain(&self, line: &[Symbol]) -> Explanation {
+        let features = plate_features(line);
+        let mut ideal = features.clone();
+        ideal[MEAN_SYMBOL_SCORE] = 1.0f32;
+        ideal[HEIGHT_VARIATION] = 0.0f32;
+        ideal[SPACING_VARIATION] = 0.0f32;
+        ideal[LINE_OFFSET] = 0.0f32;
+
+        explain_by_occlusion(|x| self.score(x), &PLATE_FEATURES, &features, &ideal)
+    }
+
+    fn plate(&self, line: Vec<Symbol>) -> NumberPlate {
+        // top and bottom edges are parallel to the line through centers
+        let (_, b) = fit_line(&line);
+        let top = line.iter().map(|s| s.bound().0.y as f32 - b * center(s).0).fold(1.0f32 / 0.0f32, f32::min);
+        let bottom = line.iter().map(|s| s.bound().1.y as f32 - b * center(s).0).fold(-1.0f32 / 0.0f32, f32::max);
+        let left = line.first().map(|s| s.bound().0.x).unwrap_or(0);
+        let right = line.last().map(|s| s.bound().1.x).unwrap_or(0);
+
+        let corner = |x: i32, offset: f32| Point { x: x, y: (offset + b * (x as f32)).round() as i32 };
+        let bound = Quad(corner(left, top), corner(right, top), corner(right, bottom), corner(left, bottom));
+
+        let score = self.score(&plate_features(&line));
+        NumberPlate::new(bound, line, score)
+    }
+
+    /// Plates formed by `candidates`, the best scored first.
+    pub fn group(&self, candidates: &[Symbol]) -> Vec<NumberPlate> {
+        let mut symbols = self.suppress(candidates);
+        symbols.sort_by_key(|s| s.bound().0.x);
+
+        let mut lines = vec![];
+        for chain in self.chains(&symbols) {
+            self.split_irregular(chain, &mut lines);
+        }
+
+        let mut plates: Vec<NumberPlate> = lines.into_iter().map(|l| self.plate(l)).collect();
+        plates.sort_by(|a, b| b.score().partial_cmp(&a.score()).unwrap_or(Ordering::Equal));
+        plates
     }
 }
 
-pub struct PlateExtractor;
+/// Classifier rescoring character candidates by their runtime features
+/// followed by second-stage features of `layout`.
+struct Verifier {
+    layout: Vec<SecondStageKind>,
+    classifier: Rc<Classifier>
+}
+
+/// Finds plates among peaks of regions of the CSER detector.
+pub struct PlateExtractor {
+    layout: Vec<FeatureKind>,
+    classifier: Rc<Classifier>,
+    verifier: Option<Verifier>,
+    pub grouper: PlateGrouper
+}
+
+impl PlateExtractor {
+    /// Regions are described by features of `layout`, which must match the
+    /// layout `classifier` was trained on. They are weighted by
+    /// `classifier` and their peaks are character candidates with its score.
+    pub fn new(layout: Vec<FeatureKind>, classifier: Rc<Classifier>) -> PlateExtractor {
+        PlateExtractor {
+            layout: layout,
+            classifier: classifier,
+            verifier: None,
+            grouper: PlateGrouper::default()
+        }
+    }
+
+    /// Candidates scored at least `min_score` of the grouping get the score
+    /// of `verifier` trained with second-stage features of `layout`.
+    pub fn with_verifier(mut self, layout: Vec<SecondStageKind>, verifier: Rc<Classifier>) -> PlateExtractor {
+        self.verifier = Some(Verifier { layout: layout, classifier: verifier });
+        self
+    }
+
+    pub fn with_grouper(mut self, grouper: PlateGrouper) -> PlateExtractor {
+        self.grouper = grouper;
+        self
+    }
+}
+
+impl ExtractPlate for PlateExtractor {
+    fn extract(&self, img: &Image<u8>) -> Vec<NumberPlate> {
+        let params = RegionParams::new(self.layout.clone()).weighted_by(self.classifier.clone());
+        let regions = Detector::detect(img, &params, &mut EmptyTrace);
+        // second-stage features share transforms of the whole image
+        let frame = self.verifier.as_ref().map(|_| Frame::new(img));
+
+        let mut candidates = vec![];
+        let mut v = vec![];
+        for r in regions.iter() {
+            for (i, &(bounds, ref features)) in r.peaks().iter().enumerate() {
+                v.clear();
+                features.value(&mut v);
+                let mut score = self.classifier.score(&v);
+
+                if let (Some(verifier), Some(frame)) = (self.verifier.as_ref(), frame.as_ref()) {
+                    if score >= self.grouper.params.min_score {
+                        SecondStageSet::compute(&verifier.layout, r.peak_points(i), frame).value(&mut v);
+                        score = verifier.classifier.score(&v);
+                    }
+                }
+
+                candidates.push(Symbol::new(bounds, score));
+            }
+        }
+
+        self.grouper.group(&candidates)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use image::Image;
+    use structures::{Point, Rect, Quad};
+    use ml::Classifier;
+    use extract::structures::Symbol;
+    use extract::cser::feature::{SecondStageKind, parse_layout};
+    use super::*;
+
+    /// Takes regions twice as tall as wide for characters.
+    struct TallRegions;
+
+    impl Classifier for TallRegions {
+        fn num_classes(&self) -> usize {
+            2
+        }
+
+        fn probabilities(&self, x: &[f32], out: &mut Vec<f32>) {
+            let p = (1.0f32 - 4.0f32 * (x[0] - 0.5f32).abs()).max(0.0f32);
+            out.clear();
+            out.push(1.0f32 - p);
+            out.push(p);
+        }
+    }
+
+    /// Five dark 6x12 characters on a gray bar that joins them, on white.
+    fn synthetic_plate() -> Image<u8> {
+        let (width, height) = (60, 20);
+        let mut data = vec![255u8; width * height];
+        for x in 2..58 {
+            for y in 9..12 {
+                data[y * width + x] = 128;
+            }
+        }
+        for i in 0..5 {
+            for x in (4 + 10 * i)..(10 + 10 * i) {
+                for y in 4..16 {
+                    data[y * width + x] = 0;
+                }
+            }
+        }
+        Image::from_data(data, width, height)
+    }
+
+    fn symbol(x: i32, y: i32, w: i32, h: i32, score: f32) -> Symbol {
+        Symbol::new(Rect(Point { x: x, y: y }, Point { x: x + w - 1, y: y + h - 1 }), score)
+    }
+
+    /// `n` symbols 8x14 with pitch 10 starting at `(x, y)`, every next one
+    /// `slope` pixels lower.
+    fn line(n: i32, x: i32, y: i32, slope: i32) -> Vec<Symbol> {
+        (0..n).map(|i| symbol(x + 10 * i, y + slope * i, 8, 14, 0.9f32)).collect()
+    }
+
+    #[test]
+    fn single_line_with_noise() {
+        let mut candidates = line(7, 20, 30, 0);
+        // low scored blob, huge blob and duplicate of a character
+        candidates.push(symbol(100, 32, 8, 14, 0.1f32));
+        candidates.push(symbol(0, 80, 60, 40, 0.9f32));
+        candidates.push(symbol(41, 31, 7, 12, 0.6f32));
+
+        let plates = PlateGrouper::default().group(&candidates);
+
+        assert_eq!(plates.len(), 1);
+        assert_eq!(plates[0].symbols(), &line(7, 20, 30, 0)[..]);
+        assert_eq!(plates[0].bound(), Quad::from_rect(Rect(Point { x: 20, y: 30 }, Point { x: 87, y: 43 })));
+        assert_eq!(plates[0].positions()[0], Point { x: 23, y: 36 });
+    }
+
+    #[test]
+    fn lines_of_different_heights_are_separate() {
+        let mut candidates = line(4, 20, 30, 0);
+        // smaller characters right behind the first line
+        candidates.extend((0..3).map(|i| symbol(60 + 6 * i, 35, 4, 6, 0.8f32)));
+        candidates.extend(line(5, 20, 80, 0));
+
+        let plates = PlateGrouper::default().group(&candidates);
+
+        let mut sizes: Vec<usize> = plates.iter().map(|p| p.symbols().len()).collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn irregular_spacing_splits_line() {
+        let mut candidates = line(4, 20, 30, 0);
+        candidates.extend(line(4, 78, 30, 0));
+        let params = GroupingParams { max_gap: 2.0f32, ..GroupingParams::default() };
+
+        let plates = PlateGrouper::new(params).group(&candidates);
+
+        assert_eq!(plates.len(), 2);
+        assert!(plates.iter().all(|p| p.symbols().len() == 4));
+    }
+
+    #[test]
+    fn slanted_line() {
+        let plates = PlateGrouper::default().group(&line(6, 20, 30, 2));
+
+        assert_eq!(plates.len(), 1);
+        // edges are parallel to the line, so corner boxes stick out a bit
+        assert_eq!(plates[0].bound(), Quad(
+            Point { x: 20, y: 29 }, Point { x: 77, y: 41 },
+            Point { x: 77, y: 54 }, Point { x: 20, y: 42 }
+        ));
+    }
+
+    #[test]
+    fn explain_irregular_spacing() {
+        let mut candidates = line(4, 20, 30, 0);
+        candidates.push(symbol(64, 30, 8, 14, 0.9f32));
+
+        let plates = PlateGrouper::default().group(&candidates);
+        let e = PlateGrouper::default().explain(plates[0].symbols());
+
+        assert_eq!(e.score, plates[0].score());
+        assert_eq!(e.features[2].name, "height_variation");
+        assert_eq!(e.features[2].contribution, 0.0f32);
+        assert_eq!(e.features[3].name, "spacing_variation");
+        assert!(e.features[3].contribution < 0.0f32);
+        assert!(e.features[1].contribution < 0.0f32);
+    }
+
+    #[test]
+    fn extract_from_region_peaks() {
+        let img = synthetic_plate();
+        let layout = parse_layout("aspect_ratio").unwrap();
+
+        let plates = PlateExtractor::new(layout, Rc::new(TallRegions)).extract(&img);
+
+        // characters are found before they merge with the bar
+        let expected: Vec<Symbol> = (0..5).map(|i| symbol(4 + 10 * i, 4, 6, 12, 1.0f32)).collect();
+        assert_eq!(plates.len(), 1);
+        assert_eq!(plates[0].symbols(), &expected[..]);
+    }
+
+    /// Takes candidates of one runtime feature followed by stroke width
+    /// statistics for characters if their strokes are wider than `0`.
+    struct StrokesWiderThan(f32);
+
+    impl Classifier for StrokesWiderThan {
+        fn num_classes(&self) -> usize {
+            2
+        }
+
+        fn probabilities(&self, x: &[f32], out: &mut Vec<f32>) {
+            assert_eq!(x.len(), 4);
+            let p = if x[1] > self.0 { 1.0f32 } else { 0.0f32 };
+            out.clear();
+            out.push(1.0f32 - p);
+            out.push(p);
+        }
+    }
+
+    #[test]
+    fn verifier_rescores_candidates() {
+        let img = synthetic_plate();
+        let layout = parse_layout("aspect_ratio").unwrap();
+        let extractor = |width: f32| {
+            PlateExtractor::new(layout.clone(), Rc::new(TallRegions))
+                .with_verifier(vec![SecondStageKind::StrokeWidth], Rc::new(StrokesWiderThan(width)))
+        };
+
+        let plates = extractor(3.0f32).extract(&img);
+        let expected: Vec<Symbol> = (0..5).map(|i| symbol(4 + 10 * i, 4, 6, 12, 1.0f32)).collect();
+        assert_eq!(plates.len(), 1);
+        assert_eq!(plates[0].symbols(), &expected[..]);
+
+        assert_eq!(extractor(10.0f32).extract(&img).len(), 0);
+    }
+
+    #[test]
+    fn nearest_link_wins_and_loser_links_further() {
+        // `a` and `b` compete for `c`, `a` is aligned with `d` too
+        let a = symbol(0, 26, 8, 14, 0.9f32);
+        let b = symbol(4, 34, 8, 14, 0.9f32);
+        let c = symbol(14, 30, 8, 14, 0.9f32);
+        let d = symbol(24, 22, 8, 14, 0.9f32);
+
+        let chains = PlateGrouper::default().chains(&[a, b, c, d]);
+
+        assert_eq!(chains, vec![vec![a, d], vec![b, c]]);
+    }
+
+    #[test]
+    fn too_few_symbols() {
+        assert_eq!(PlateGrouper::default().group(&line(2, 20, 30, 0)).len(), 0);
+    }
+}
@@ -17,6 +17,8 @@ pub trait ExtremalRegion : Sized {
 pub trait RegionDetector {
     type Region: ExtremalRegion;
     type Trace: Trace<Self::Region>;
+    /// Configuration of the detected regions, e.g. their feature layout.
+    type Params;
 
-    fn detect(img: &Image<u8>, trace: &mut Self::Trace) -> Vec<Self::Region>;
+    fn detect(img: &Image<u8>, params: &Self::Params, trace: &mut Self::Trace) -> Vec<Self::Region>;
 }
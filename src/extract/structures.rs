@@ -1,10 +1,58 @@
 use structures::{Rect, Point, Quad};
 
+/// Character candidate, `score` is the probability of it being a character.
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Symbol {
-    bound: Rect
+    bound: Rect,
+    score: f32
 }
 
+impl Symbol {
+    pub fn new(bound: Rect, score: f32) -> Symbol {
+        Symbol { bound: bound, score: score }
+    }
+
+    pub fn bound(&self) -> Rect {
+        self.bound
+    }
+
+    pub fn score(&self) -> f32 {
+        self.score
+    }
+
+    pub fn center(&self) -> Point {
+        Point { x: (self.bound.0.x + self.bound.1.x) / 2, y: (self.bound.0.y + self.bound.1.y) / 2 }
+    }
+}
+
+/// Line of symbols found in an image, symbols are ordered from left to
+/// right.
+#[derive(Debug, Clone, PartialEq)]
 pub struct NumberPlate {
     bound: Quad,
-    symbols: Vec<Point>
+    symbols: Vec<Symbol>,
+    score: f32
+}
+
+impl NumberPlate {
+    pub fn new(bound: Quad, symbols: Vec<Symbol>, score: f32) -> NumberPlate {
+        NumberPlate { bound: bound, symbols: symbols, score: score }
+    }
+
+    pub fn bound(&self) -> Quad {
+        self.bound
+    }
+
+    pub fn symbols<'a>(&'a self) -> &'a [Symbol] {
+        &self.symbols[..]
+    }
+
+    /// Positions of symbols, their centers.
+    pub fn positions(&self) -> Vec<Point> {
+        self.symbols.iter().map(|s| s.center()).collect()
+    }
+
+    pub fn score(&self) -> f32 {
+        self.score
+    }
 }
@@ -0,0 +1,54 @@
+use super::image::Image;
+
+/// Sobel gradient magnitude. Border pixels are computed with clamped
+/// coordinates.
+pub fn sobel_magnitude(img: &Image<u8>) -> Image<f32> {
+    let (w, h) = (img.width() as i32, img.height() as i32);
+    let mut data: Vec<f32> = Vec::with_capacity((w * h) as usize);
+
+    let px = |x: i32, y: i32| -> f32 {
+        let cx = if x < 0 { 0 } else if x >= w { w - 1 } else { x };
+        let cy = if y < 0 { 0 } else if y >= h { h - 1 } else { y };
+        img[(cx, cy)] as f32
+    };
+
+    for y in 0..h {
+        for x in 0..w {
+            let gx = (px(x + 1, y - 1) + 2.0f32 * px(x + 1, y) + px(x + 1, y + 1)) -
+                     (px(x - 1, y - 1) + 2.0f32 * px(x - 1, y) + px(x - 1, y + 1));
+            let gy = (px(x - 1, y + 1) + 2.0f32 * px(x, y + 1) + px(x + 1, y + 1)) -
+                     (px(x - 1, y - 1) + 2.0f32 * px(x, y - 1) + px(x + 1, y - 1));
+            data.push((gx * gx + gy * gy).sqrt());
+        }
+    }
+
+    Image::from_data(data, w as usize, h as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Image;
+
+    #[test]
+    fn flat_image_has_no_gradient() {
+        let img: Image<u8> = Image::from_data(vec![7; 12], 4, 3);
+        let g = sobel_magnitude(&img);
+        assert!(g.data().iter().all(|v| *v == 0.0f32));
+    }
+
+    #[test]
+    fn vertical_edge() {
+        let img: Image<u8> = Image::from_data(vec![
+            0, 0, 10, 10,
+            0, 0, 10, 10,
+            0, 0, 10, 10,
+        ], 4, 3);
+
+        let g = sobel_magnitude(&img);
+
+        assert_eq!(g[(1, 1)], 40.0f32);
+        assert_eq!(g[(2, 1)], 40.0f32);
+        assert_eq!(g[(0, 1)], 0.0f32);
+    }
+}
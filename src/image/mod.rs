@@ -1,6 +1,7 @@
 pub mod image;
 pub mod pixel;
 pub mod io;
+pub mod gradient;
 
 pub use self::image::Image;
 pub use pd_image::ImageResult;
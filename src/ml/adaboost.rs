@@ -0,0 +1,265 @@
+use std::cmp::Ordering;
+
+use super::classifier::{Classifier, is_character};
+use super::logistic::sigmoid;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AdaBoostParams {
+    pub rounds: usize,
+    /// Added to weight sums of stump bins to avoid infinite outputs.
+    pub smoothing: f32,
+    /// Maximum number of candidate thresholds per feature and round.
+    pub max_thresholds: usize
+}
+
+impl Default for AdaBoostParams {
+    fn default() -> AdaBoostParams {
+        AdaBoostParams {
+            rounds: 100,
+            smoothing: 1e-4f32,
+            max_thresholds: 64
+        }
+    }
+}
+
+/// Real AdaBoost weak learner: outputs `left` if `x[feature] < threshold`
+/// and `right` otherwise.
+#[derive(Debug, Copy, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Stump {
+    pub feature: usize,
+    pub threshold: f32,
+    pub left: f32,
+    pub right: f32
+}
+
+impl Stump {
+    pub fn eval(&self, x: &[f32]) -> f32 {
+        if x[self.feature] < self.threshold { self.left } else { self.right }
+    }
+}
+
+/// Sum of all stumps of a single feature, which is a piecewise constant
+/// function of the feature value: `values[i]` is the output for values
+/// between `thresholds[i - 1]` and `thresholds[i]`.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+struct FeatureTable {
+    thresholds: Vec<f32>,
+    values: Vec<f32>
+}
+
+impl FeatureTable {
+    fn new(stumps: &[Stump]) -> FeatureTable {
+        let mut sorted = stumps.to_vec();
+        sorted.sort_by(|a, b| a.threshold.partial_cmp(&b.threshold).unwrap_or(Ordering::Equal));
+
+        let mut values = vec![sorted.iter().fold(0.0f32, |a, s| a + s.left)];
+        for s in sorted.iter() {
+            let last = *values.last().unwrap();
+            values.push(last - s.left + s.right);
+        }
+
+        FeatureTable {
+            thresholds: sorted.iter().map(|s| s.threshold).collect(),
+            values: values
+        }
+    }
+
+    fn eval(&self, v: f32) -> f32 {
+        // number of thresholds that are `<= v`
+        let (mut lo, mut hi) = (0, self.thresholds.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self.thresholds[mid] <= v { lo = mid + 1 } else { hi = mid }
+        }
+        self.values[lo]
+    }
+}
+
+/// Real AdaBoost over decision stumps, `p(character) = sigmoid(2 * sum h(x))`.
+///
+/// Stumps of the same feature are compiled into a lookup table, so scoring
+/// a region costs a binary search per feature regardless of the number of
+/// boosting rounds, and contribution of each feature can be evaluated and
+/// cached separately.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct AdaBoost {
+    stumps: Vec<Stump>,
+    tables: Vec<FeatureTable>
+}
+
+impl AdaBoost {
+    pub fn from_stumps(num_features: usize, stumps: Vec<Stump>) -> AdaBoost {
+        let tables = (0..num_features)
+            .map(|f| {
+                let fs: Vec<Stump> = stumps.iter().filter(|s| s.feature == f).cloned().collect();
+                FeatureTable::new(&fs)
+            })
+            .collect();
+
+        AdaBoost { stumps: stumps, tables: tables }
+    }
+
+    pub fn train(rows: &[Vec<f32>], labels: &[usize], params: &AdaBoostParams) -> AdaBoost {
+        debug_assert!(rows.len() == labels.len());
+
+        let n = rows.len();
+        let num_features = rows.first().map(|r| r.len()).unwrap_or(0);
+        let y: Vec<f32> = labels.iter().map(|l| if is_character(*l) { 1.0f32 } else { -1.0f32 }).collect();
+        let mut w = vec![1.0f32 / (n as f32); n];
+
+        // samples sorted by each feature, computed once
+        let order: Vec<Vec<usize>> = (0..num_features)
+            .map(|f| {
+                let mut idx: Vec<usize> = (0..n).collect();
+                idx.sort_by(|a, b| rows[*a][f].partial_cmp(&rows[*b][f]).unwrap_or(Ordering::Equal));
+                idx
+            })
+            .collect();
+
+        let mut stumps = vec![];
+        for _ in 0..params.rounds {
+            let best = (0..num_features)
+                .filter_map(|f| best_stump(f, rows, &order[f], &y, &w, params))
+                .fold(None, |best: Option<(f32, Stump)>, c| match best {
+                    Some(b) if b.0 <= c.0 => Some(b),
+                    _ => Some(c)
+                });
+
+            let stump = match best {
+                Some((_, s)) => s,
+                None => break
+            };
+
+            let mut sum = 0.0f32;
+            for i in 0..n {
+                w[i] *= (-y[i] * stump.eval(&rows[i])).exp();
+                sum += w[i];
+            }
+            for wi in w.iter_mut() {
+                *wi /= sum;
+            }
+
+            stumps.push(stump);
+        }
+
+        AdaBoost::from_stumps(num_features, stumps)
+    }
+
+    pub fn stumps<'a>(&'a self) -> &'a [Stump] {
+        &self.stumps[..]
+    }
+
+    /// Contribution of feature `f` with value `v` to the margin.
+    pub fn contribution(&self, f: usize, v: f32) -> f32 {
+        self.tables[f].eval(v)
+    }
+
+    pub fn margin(&self, x: &[f32]) -> f32 {
+        debug_assert!(x.len() == self.tables.len());
+        self.tables.iter().zip(x.iter()).fold(0.0f32, |a, (t, v)| a + t.eval(*v))
+    }
+}
+
+/// Finds stump on feature `f` minimizing Real AdaBoost normalization
+/// factor `Z = 2 * sum_j sqrt(W+_j * W-_j)`. Returns `(Z, stump)`.
+fn best_stump(
+    f: usize,
+    rows: &[Vec<f32>],
+    order: &[usize],
+    y: &[f32],
+    w: &[f32],
+    params: &AdaBoostParams
+) -> Option<(f32, Stump)> {
+    let (total_pos, total_neg) = order.iter().fold((0.0f32, 0.0f32), |(p, n), i| {
+        if y[*i] > 0.0f32 { (p + w[*i], n) } else { (p, n + w[*i]) }
+    });
+
+    let step = ((order.len() as f32) / (params.max_thresholds as f32)).ceil().max(1.0f32) as usize;
+    let eps = params.smoothing;
+
+    let mut best: Option<(f32, Stump)> = None;
+    let (mut left_pos, mut left_neg) = (0.0f32, 0.0f32);
+
+    for k in 0..order.len() {
+        let i = order[k];
+        if y[i] > 0.0f32 { left_pos += w[i] } else { left_neg += w[i] }
+
+        if k + 1 == order.len() || k % step != step - 1 {
+            continue;
+        }
+
+        let (v, next) = (rows[i][f], rows[order[k + 1]][f]);
+        if v == next {
+            continue;
+        }
+
+        let (right_pos, right_neg) = (total_pos - left_pos, total_neg - left_neg);
+        let z = 2.0f32 * ((left_pos * left_neg).sqrt() + (right_pos * right_neg).sqrt());
+
+        if best.map(|b| z < b.0).unwrap_or(true) {
+            best = Some((z, Stump {
+                feature: f,
+                threshold: 0.5f32 * (v + next),
+                left: 0.5f32 * ((left_pos + eps) / (left_neg + eps)).ln(),
+                right: 0.5f32 * ((right_pos + eps) / (right_neg + eps)).ln()
+            }));
+        }
+    }
+
+    best
+}
+
+impl Classifier for AdaBoost {
+    fn num_classes(&self) -> usize {
+        2
+    }
+
+    fn probabilities(&self, x: &[f32], out: &mut Vec<f32>) {
+        let p = sigmoid(2.0f32 * self.margin(x));
+        out.clear();
+        out.push(1.0f32 - p);
+        out.push(p);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ml::classifier::Classifier;
+
+    #[test]
+    fn tables_match_stumps() {
+        let stumps = vec![
+            Stump { feature: 0, threshold: 1.0f32, left: -1.0f32, right: 0.5f32 },
+            Stump { feature: 1, threshold: 0.0f32, left: 0.25f32, right: -0.25f32 },
+            Stump { feature: 0, threshold: -2.0f32, left: 0.75f32, right: -0.5f32 },
+        ];
+        let model = AdaBoost::from_stumps(2, stumps.clone());
+
+        for x0 in [-3.0f32, -2.0f32, 0.0f32, 1.0f32, 2.0f32].iter() {
+            for x1 in [-1.0f32, 0.0f32, 1.0f32].iter() {
+                let x = [*x0, *x1];
+                let expected = stumps.iter().fold(0.0f32, |a, s| a + s.eval(&x));
+                assert_eq!(model.margin(&x), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn separable_2d() {
+        // positive iff both features are large
+        let mut rows = vec![];
+        let mut labels = vec![];
+        for a in 0..10 {
+            for b in 0..10 {
+                rows.push(vec![a as f32, b as f32]);
+                labels.push(if a >= 5 && b >= 3 { 1 } else { 0 });
+            }
+        }
+
+        let model = AdaBoost::train(&rows, &labels, &AdaBoostParams::default());
+
+        let predicted: Vec<usize> = model.predict_batch(&rows).iter().map(|p| p.class).collect();
+        assert_eq!(predicted, labels);
+    }
+}
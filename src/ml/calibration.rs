@@ -0,0 +1,262 @@
+use std::cmp::Ordering;
+
+use super::classifier::{is_character, character_probability};
+use super::logistic::sigmoid;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CalibrationKind {
+    Platt,
+    Isotonic
+}
+
+impl CalibrationKind {
+    pub fn from_name(name: &str) -> Option<CalibrationKind> {
+        match name {
+            "platt" => Some(CalibrationKind::Platt),
+            "isotonic" => Some(CalibrationKind::Isotonic),
+            _ => None
+        }
+    }
+}
+
+/// Monotonic mapping of classifier scores to probabilities of being a
+/// character. Fitted on scores of samples the classifier wasn't trained on.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Calibration {
+    Identity,
+    /// `sigmoid(a * logit(score) + b)`.
+    Platt { a: f32, b: f32 },
+    /// Piecewise constant: scores starting at `thresholds[i]` map to `values[i]`.
+    Isotonic { thresholds: Vec<f32>, values: Vec<f32> }
+}
+
+static EPS: f32 = 1e-6;
+
+fn logit(p: f32) -> f32 {
+    let p = p.max(EPS).min(1.0f32 - EPS);
+    (p / (1.0f32 - p)).ln()
+}
+
+/// `ln(1 + e^z)` without overflow.
+fn log1pexp(z: f64) -> f64 {
+    if z > 0.0 { z + (-z).exp().ln_1p() } else { z.exp().ln_1p() }
+}
+
+impl Calibration {
+    pub fn fit(kind: CalibrationKind, scores: &[f32], labels: &[usize]) -> Calibration {
+        match kind {
+            CalibrationKind::Platt => Calibration::fit_platt(scores, labels),
+            CalibrationKind::Isotonic => Calibration::fit_isotonic(scores, labels)
+        }
+    }
+
+    /// Platt scaling fitted with Newton's method, using Platt's smoothed
+    /// targets to avoid overfitting on separable data.
+    pub fn fit_platt(scores: &[f32], labels: &[usize]) -> Calibration {
+        let x: Vec<f64> = scores.iter().map(|s| logit(*s) as f64).collect();
+        let positives = labels.iter().filter(|l| is_character(**l)).count() as f64;
+        let negatives = (labels.len() as f64) - positives;
+
+        let (hi, lo) = ((positives + 1.0) / (positives + 2.0), 1.0 / (negatives + 2.0));
+        let t: Vec<f64> = labels.iter().map(|l| if is_character(*l) { hi } else { lo }).collect();
+
+        let nll = |a: f64, b: f64| -> f64 {
+            x.iter().zip(t.iter()).fold(0.0, |acc, (x, t)| {
+                let z = a * x + b;
+                acc + t * log1pexp(-z) + (1.0 - t) * log1pexp(z)
+            })
+        };
+
+        let (mut a, mut b) = (1.0f64, ((positives + 1.0) / (negatives + 1.0)).ln());
+        let mut f = nll(a, b);
+
+        for _ in 0..100 {
+            let (mut ga, mut gb, mut haa, mut hab, mut hbb) = (0.0f64, 0.0f64, 1e-12f64, 0.0f64, 1e-12f64);
+            for (x, t) in x.iter().zip(t.iter()) {
+                let p = 1.0 / (1.0 + (-(a * x + b)).exp());
+                let d = p * (1.0 - p);
+                ga += (p - t) * x;
+                gb += p - t;
+                haa += d * x * x;
+                hab += d * x;
+                hbb += d;
+            }
+
+            if ga.abs() < 1e-5 && gb.abs() < 1e-5 {
+                break;
+            }
+
+            let det = haa * hbb - hab * hab;
+            let (da, db) = (-(hbb * ga - hab * gb) / det, -(haa * gb - hab * ga) / det);
+
+            // backtracking line search
+            let mut step = 1.0f64;
+            while step >= 1e-10 {
+                let nf = nll(a + step * da, b + step * db);
+                if nf < f + 1e-4 * step * (ga * da + gb * db) {
+                    a += step * da;
+                    b += step * db;
+                    f = nf;
+                    break;
+                }
+                step /= 2.0;
+            }
+
+            if step < 1e-10 {
+                break;
+            }
+        }
+
+        Calibration::Platt { a: a as f32, b: b as f32 }
+    }
+
+    /// Isotonic regression fitted with pool adjacent violators.
+    pub fn fit_isotonic(scores: &[f32], labels: &[usize]) -> Calibration {
+        let mut order: Vec<usize> = (0..scores.len()).collect();
+        order.sort_by(|a, b| scores[*a].partial_cmp(&scores[*b]).unwrap_or(Ordering::Equal));
+
+        // (lowest score, sum of targets, count), equal scores must map to
+        // the same value so they start as a single block
+        let mut points: Vec<(f32, f32, f32)> = vec![];
+        for i in order {
+            let y = if is_character(labels[i]) { 1.0f32 } else { 0.0f32 };
+            if points.last().map(|p| p.0 == scores[i]).unwrap_or(false) {
+                let last = points.last_mut().unwrap();
+                last.1 += y;
+                last.2 += 1.0f32;
+            } else {
+                points.push((scores[i], y, 1.0f32));
+            }
+        }
+
+        let mut blocks: Vec<(f32, f32, f32)> = vec![];
+        for p in points {
+            blocks.push(p);
+
+            while blocks.len() > 1 {
+                let n = blocks.len();
+                let (prev, last) = (blocks[n - 2], blocks[n - 1]);
+                if prev.1 / prev.2 < last.1 / last.2 {
+                    break;
+                }
+                blocks.pop();
+                blocks[n - 2] = (prev.0, prev.1 + last.1, prev.2 + last.2);
+            }
+        }
+
+        Calibration::Isotonic {
+            thresholds: blocks.iter().map(|b| b.0).collect(),
+            values: blocks.iter().map(|b| b.1 / b.2).collect()
+        }
+    }
+
+    pub fn apply(&self, score: f32) -> f32 {
+        match *self {
+            Calibration::Identity => score,
+            Calibration::Platt { a, b } => sigmoid(a * logit(score) + b),
+            Calibration::Isotonic { ref thresholds, ref values } => {
+                if values.len() == 0 {
+                    return score;
+                }
+                // last block starting at or below the score
+                let i = thresholds.iter().take_while(|t| **t <= score).count();
+                values[if i > 0 { i - 1 } else { 0 }]
+            }
+        }
+    }
+
+    /// Calibrates class probabilities: character classes together get
+    /// the calibrated score and the rest gets `1 - calibrated score`, both
+    /// split proportionally among their classes.
+    pub fn apply_probabilities(&self, p: &mut [f32]) {
+        if *self == Calibration::Identity || p.len() < 2 {
+            return;
+        }
+
+        let score = character_probability(p);
+        let calibrated = self.apply(score);
+        let characters = (0..p.len()).filter(|c| is_character(*c)).count() as f32;
+        let others = (p.len() as f32) - characters;
+
+        for (c, v) in p.iter_mut().enumerate() {
+            let (mass, target, n) = if is_character(c) {
+                (score, calibrated, characters)
+            } else {
+                (1.0f32 - score, 1.0f32 - calibrated, others)
+            };
+            *v = if mass > 0.0f32 { *v * target / mass } else { target / n };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ml::rng::XorShift;
+    use ml::logistic::sigmoid;
+
+    #[test]
+    fn platt_recovers_mapping() {
+        // true probability is sigmoid(2 * logit(score) - 1)
+        let mut rng = XorShift::new(5);
+        let mut scores = vec![];
+        let mut labels = vec![];
+        for _ in 0..5000 {
+            let s = 0.01f32 + 0.98f32 * rng.uniform();
+            let p = sigmoid(2.0f32 * (s / (1.0f32 - s)).ln() - 1.0f32);
+            scores.push(s);
+            labels.push(if rng.uniform() < p { 1 } else { 0 });
+        }
+
+        match Calibration::fit_platt(&scores, &labels) {
+            Calibration::Platt { a, b } => {
+                assert!((a - 2.0f32).abs() < 0.2f32, "a = {}", a);
+                assert!((b + 1.0f32).abs() < 0.2f32, "b = {}", b);
+            },
+            c => panic!("expected Platt, got {:?}", c)
+        }
+    }
+
+    #[test]
+    fn isotonic_is_monotonic() {
+        let scores = vec![0.1f32, 0.2f32, 0.3f32, 0.4f32, 0.5f32, 0.6f32];
+        let labels = vec![0, 1, 0, 0, 1, 1];
+
+        let c = Calibration::fit_isotonic(&scores, &labels);
+
+        assert_eq!(c, Calibration::Isotonic {
+            thresholds: vec![0.1f32, 0.2f32, 0.5f32],
+            values: vec![0.0f32, 1.0f32 / 3.0f32, 1.0f32]
+        });
+        assert_eq!(c.apply(0.0f32), 0.0f32);
+        assert_eq!(c.apply(0.35f32), 1.0f32 / 3.0f32);
+        assert_eq!(c.apply(0.9f32), 1.0f32);
+    }
+
+    #[test]
+    fn probabilities_keep_class_ratios() {
+        let c = Calibration::Isotonic { thresholds: vec![0.0f32], values: vec![0.8f32] };
+        let mut p = vec![0.6f32, 0.3f32, 0.1f32];
+
+        c.apply_probabilities(&mut p);
+
+        assert!((p[0] - 0.2f32).abs() < 1e-6);
+        assert!((p[1] - 0.6f32).abs() < 1e-6);
+        assert!((p[2] - 0.2f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn probabilities_of_border_follow_background() {
+        let c = Calibration::Isotonic { thresholds: vec![0.0f32], values: vec![0.5f32] };
+        // background, character, digit, letter, border
+        let mut p = vec![0.1f32, 0.0f32, 0.6f32, 0.2f32, 0.1f32];
+
+        c.apply_probabilities(&mut p);
+
+        assert!((p[0] - 0.25f32).abs() < 1e-6);
+        assert!((p[1] - 0.0f32).abs() < 1e-6);
+        assert!((p[2] - 0.375f32).abs() < 1e-6);
+        assert!((p[3] - 0.125f32).abs() < 1e-6);
+        assert!((p[4] - 0.25f32).abs() < 1e-6);
+    }
+}
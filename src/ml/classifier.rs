@@ -1,3 +1,114 @@
-pub trait Classifier<A> {
-    fn classify(&self) -> A;
+use super::normalize::Normalizer;
+
+/// Index of the background (non-character) class. Binary classifiers use
+/// `CHARACTER` for the other class.
+pub static BACKGROUND: usize = 0;
+
+/// Character of unknown kind.
+pub static CHARACTER: usize = 1;
+
+/// Region classes of multi-class classifiers. Ground truth without
+/// character texts and plate boxes produces only `BACKGROUND` and
+/// `CHARACTER` labels.
+pub static DIGIT: usize = 2;
+pub static LETTER: usize = 3;
+/// Blob of the plate frame or border.
+pub static BORDER: usize = 4;
+
+pub fn is_character(class: usize) -> bool {
+    class == CHARACTER || class == DIGIT || class == LETTER
+}
+
+static CLASS_NAMES: [&'static str; 5] = ["background", "character", "digit", "letter", "border"];
+
+/// Name of a region class for reports.
+pub fn class_name(class: usize) -> &'static str {
+    CLASS_NAMES.get(class).cloned().unwrap_or("unknown")
+}
+
+/// Total probability of character classes.
+pub fn character_probability(p: &[f32]) -> f32 {
+    p.iter().enumerate().filter(|&(c, _)| is_character(c)).fold(0.0f32, |a, (_, v)| a + v)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Prediction {
+    pub class: usize,
+    pub probability: f32
+}
+
+pub trait Classifier {
+    fn num_classes(&self) -> usize;
+
+    /// Writes probability of every class for feature vector `x` into `out`.
+    fn probabilities(&self, x: &[f32], out: &mut Vec<f32>);
+
+    /// Most probable class.
+    fn predict(&self, x: &[f32]) -> Prediction {
+        let mut p = vec![];
+        self.probabilities(x, &mut p);
+        argmax(&p)
+    }
+
+    fn predict_batch(&self, xs: &[Vec<f32>]) -> Vec<Prediction> {
+        xs.iter().map(|x| self.predict(x)).collect()
+    }
+
+    /// Probability that `x` is a character of any kind.
+    fn score(&self, x: &[f32]) -> f32 {
+        let mut p = vec![];
+        self.probabilities(x, &mut p);
+        character_probability(&p)
+    }
+
+    /// `probabilities` that may keep intermediate vectors in `scratch`, so
+    /// that classifying many vectors doesn't allocate.
+    fn probabilities_with(&self, x: &[f32], out: &mut Vec<f32>, _: &mut Vec<f32>) {
+        self.probabilities(x, out);
+    }
+
+    /// `score` that reuses `out` for the probabilities and `scratch` as
+    /// `probabilities_with` does.
+    fn score_with(&self, x: &[f32], out: &mut Vec<f32>, scratch: &mut Vec<f32>) -> f32 {
+        self.probabilities_with(x, out, scratch);
+        character_probability(out)
+    }
+}
+
+/// Most probable class of probabilities `p`, which must not be empty.
+pub fn argmax(p: &[f32]) -> Prediction {
+    debug_assert!(p.len() > 0, "argmax of no probabilities");
+    let mut best = Prediction { class: 0, probability: p[0] };
+    for (class, probability) in p.iter().cloned().enumerate().skip(1) {
+        if probability > best.probability {
+            best = Prediction { class: class, probability: probability };
+        }
+    }
+    best
+}
+
+/// Classifier applied to normalized feature vectors.
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+pub struct Normalized<C: Classifier> {
+    pub normalizer: Normalizer,
+    pub classifier: C
+}
+
+impl<C: Classifier> Classifier for Normalized<C> {
+    fn num_classes(&self) -> usize {
+        self.classifier.num_classes()
+    }
+
+    fn probabilities(&self, x: &[f32], out: &mut Vec<f32>) {
+        let mut nx = x.to_vec();
+        self.normalizer.apply(&mut nx);
+        self.classifier.probabilities(&nx, out);
+    }
+
+    fn probabilities_with(&self, x: &[f32], out: &mut Vec<f32>, scratch: &mut Vec<f32>) {
+        scratch.clear();
+        scratch.extend_from_slice(x);
+        self.normalizer.apply(scratch);
+        self.classifier.probabilities(scratch, out);
+    }
 }
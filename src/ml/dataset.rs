@@ -0,0 +1,369 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::mem;
+use std::io::prelude::*;
+use std::io::{BufReader, BufWriter};
+
+use bincode::rustc_serialize::{EncodingError, DecodingError};
+
+use structures::{Point, Rect};
+use extract::cser::feature::{Schema, Column, SchemaMismatch};
+use super::classifier::is_character;
+use super::model::{ModelError, write_versioned, read_versioned};
+
+/// Feature vector of a single detected region with its label.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Sample {
+    pub features: Vec<f32>,
+    pub label: usize,
+    pub image: String,
+    pub bounds: Rect,
+    pub thres: i32
+}
+
+/// Identity of a detected region: image, bounds and threshold.
+pub type RegionKey = (String, Rect, i32);
+
+impl Sample {
+    /// Whether both samples come from the same detected region.
+    pub fn same_region(&self, other: &Sample) -> bool {
+        self.image == other.image && self.bounds == other.bounds && self.thres == other.thres
+    }
+
+    pub fn region_key(&self) -> RegionKey {
+        (self.image.clone(), self.bounds, self.thres)
+    }
+}
+
+/// Labeled feature vectors together with the schema of their columns.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Dataset {
+    pub schema: Schema,
+    pub samples: Vec<Sample>
+}
+
+impl Dataset {
+    pub fn new(schema: Schema) -> Dataset {
+        Dataset {
+            schema: schema,
+            samples: vec![]
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn push(&mut self, sample: Sample) {
+        debug_assert!(sample.features.len() == self.schema.len());
+        self.samples.push(sample);
+    }
+
+    /// Appends samples of `other`, which must have the same schema.
+    pub fn extend(&mut self, other: Dataset) -> Result<(), SchemaMismatch> {
+        try!(self.schema.check(&other.schema));
+        self.samples.extend(other.samples.into_iter());
+        Ok(())
+    }
+
+    /// Keys of all regions in the dataset, for fast lookups of samples
+    /// of the same region.
+    pub fn region_keys(&self) -> HashSet<RegionKey> {
+        self.samples.iter().map(|s| s.region_key()).collect()
+    }
+
+    pub fn rows(&self) -> Vec<Vec<f32>> {
+        self.samples.iter().map(|s| s.features.clone()).collect()
+    }
+
+    pub fn labels(&self) -> Vec<usize> {
+        self.samples.iter().map(|s| s.label).collect()
+    }
+
+    pub fn count(&self, label: usize) -> usize {
+        self.samples.iter().filter(|s| s.label == label).count()
+    }
+
+    /// Number of samples of any character class.
+    pub fn num_characters(&self) -> usize {
+        self.samples.iter().filter(|s| is_character(s.label)).count()
+    }
+}
+
+static MAGIC: &'static str = "nprs-dataset";
+
+/// Version of the binary dataset format.
+pub static DATASET_VERSION: u32 = 1;
+
+/// Columns of CSV files preceding feature columns.
+static CSV_META: [&'static str; 7] = ["image", "x1", "y1", "x2", "y2", "thres", "label"];
+
+#[derive(Debug)]
+pub enum DatasetError {
+    IoError(io::Error),
+    EncodeError(EncodingError),
+    DecodeError(DecodingError),
+    NotADataset,
+    UnsupportedVersion(u32),
+    CsvError { line: usize, message: String }
+}
+
+pub type DatasetResult<T> = Result<T, DatasetError>;
+
+/// Errors of the versioned binary format shared with models.
+fn versioned_error(e: ModelError) -> DatasetError {
+    match e {
+        ModelError::IoError(e) => DatasetError::IoError(e),
+        ModelError::EncodeError(e) => DatasetError::EncodeError(e),
+        ModelError::DecodeError(e) => DatasetError::DecodeError(e),
+        ModelError::NotAModel => DatasetError::NotADataset,
+        ModelError::UnsupportedVersion(v) => DatasetError::UnsupportedVersion(v),
+        ModelError::SchemaMismatch(_) => unreachable!("versioned files don't check schemas")
+    }
+}
+
+fn csv_error<T>(line: usize, message: String) -> DatasetResult<T> {
+    Err(DatasetError::CsvError { line: line, message: message })
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace("\"", "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Splits CSV record into fields, `"` quotes fields and `""` escapes quotes.
+/// Fails if a quoted field isn't terminated, e.g. if it continues on the
+/// next line.
+fn csv_split(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => { chars.next(); field.push('"'); },
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(mem::replace(&mut field, String::new())),
+            (c, _) => field.push(c)
+        }
+    }
+
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+impl Dataset {
+    /// Writes samples as CSV with a header row: image, bounds, threshold,
+    /// label and then every feature column of the schema.
+    pub fn write_csv<W: Write>(&self, w: &mut W) -> DatasetResult<()> {
+        let mut header: Vec<String> = CSV_META.iter().map(|h| h.to_string()).collect();
+        header.extend(self.schema.names().iter().map(|n| csv_escape(n)));
+        try!(writeln!(w, "{}", header.join(",")).map_err(|e| DatasetError::IoError(e)));
+
+        for s in self.samples.iter() {
+            let Rect(tl, br) = s.bounds;
+            let mut fields = vec![
+                csv_escape(&s.image),
+                tl.x.to_string(), tl.y.to_string(), br.x.to_string(), br.y.to_string(),
+                s.thres.to_string(), s.label.to_string()
+            ];
+            fields.extend(s.features.iter().map(|f| f.to_string()));
+            try!(writeln!(w, "{}", fields.join(",")).map_err(|e| DatasetError::IoError(e)));
+        }
+
+        Ok(())
+    }
+
+    /// Reads CSV written by `write_csv`. Column ranges are not stored in
+    /// CSV, so the schema only has column names. Quoted fields may span
+    /// several lines.
+    pub fn read_csv<R: Read>(r: &mut R) -> DatasetResult<Dataset> {
+        let mut lines = BufReader::new(r).lines();
+
+        let header = match lines.next() {
+            Some(l) => try!(l.map_err(|e| DatasetError::IoError(e))),
+            None => return csv_error(1, "missing header".to_string())
+        };
+        let header = try!(csv_split(&header).or_else(|e| csv_error(1, e)));
+        let has_meta = header.len() >= CSV_META.len() &&
+            header.iter().zip(CSV_META.iter()).all(|(h, m)| &h[..] == *m);
+        if !has_meta {
+            return csv_error(1, format!("header must start with {}", CSV_META.join(",")));
+        }
+
+        let schema = Schema {
+            columns: header[CSV_META.len()..].iter().map(|n| Column::new(n, None, None)).collect()
+        };
+        let mut dataset = Dataset::new(schema);
+
+        // record and number of the line it starts on
+        let mut record = String::new();
+        let mut n = 0;
+
+        for (i, line) in lines.enumerate() {
+            let line = try!(line.map_err(|e| DatasetError::IoError(e)));
+            if record.len() == 0 {
+                if line.trim().len() == 0 {
+                    continue;
+                }
+                record = line;
+                n = i + 2;
+            } else {
+                record.push('\n');
+                record.push_str(&line);
+            }
+
+            let fields = match csv_split(&record) {
+                Ok(fields) => fields,
+                // quoted field goes on
+                Err(_) => continue
+            };
+            record.clear();
+
+            if fields.len() != header.len() {
+                return csv_error(n, format!("expected {} fields, got {}", header.len(), fields.len()));
+            }
+
+            let ints: Result<Vec<i32>, _> = fields[1..6].iter().map(|f| f.trim().parse::<i32>()).collect();
+            let ints = try!(ints.or_else(|e| csv_error(n, e.to_string())));
+            let label = try!(fields[6].trim().parse::<usize>().or_else(|e| csv_error(n, e.to_string())));
+            let features: Result<Vec<f32>, _> = fields[CSV_META.len()..].iter().map(|f| f.trim().parse::<f32>()).collect();
+            let features = try!(features.or_else(|e| csv_error(n, e.to_string())));
+
+            dataset.push(Sample {
+                features: features,
+                label: label,
+                image: fields[0].clone(),
+                bounds: Rect(Point { x: ints[0], y: ints[1] }, Point { x: ints[2], y: ints[3] }),
+                thres: ints[4]
+            });
+        }
+
+        if record.len() > 0 {
+            return csv_error(n, "unterminated quote".to_string());
+        }
+
+        Ok(dataset)
+    }
+
+    pub fn write_binary<W: Write>(&self, w: &mut W) -> DatasetResult<()> {
+        write_versioned(MAGIC, DATASET_VERSION, self, w).map_err(versioned_error)
+    }
+
+    pub fn read_binary<R: Read>(r: &mut R) -> DatasetResult<Dataset> {
+        read_versioned(MAGIC, DATASET_VERSION, r).map_err(versioned_error)
+    }
+
+    /// Saves as CSV if `path` ends with `.csv` and in binary format otherwise.
+    pub fn save(&self, path: &str) -> DatasetResult<()> {
+        let f = try!(File::create(path).map_err(|e| DatasetError::IoError(e)));
+        let mut w = BufWriter::new(f);
+        if path.ends_with(".csv") {
+            self.write_csv(&mut w)
+        } else {
+            self.write_binary(&mut w)
+        }
+    }
+
+    pub fn load(path: &str) -> DatasetResult<Dataset> {
+        let f = try!(File::open(path).map_err(|e| DatasetError::IoError(e)));
+        let mut r = BufReader::new(f);
+        if path.ends_with(".csv") {
+            Dataset::read_csv(&mut r)
+        } else {
+            Dataset::read_binary(&mut r)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use structures::{Point, Rect};
+    use extract::cser::feature::{Schema, Column};
+    use ml::classifier::{BACKGROUND, CHARACTER};
+    use ml::model::write_versioned;
+    use super::*;
+
+    fn dataset() -> Dataset {
+        let mut d = Dataset::new(Schema {
+            columns: vec![Column::new("a", Some(0.0f32), None), Column::new("b", None, None)]
+        });
+        d.push(Sample {
+            features: vec![0.1f32, -3.25e-7f32],
+            label: CHARACTER,
+            image: "plates/\"one\",\ntwo.png".to_string(),
+            bounds: Rect(Point { x: 1, y: 2 }, Point { x: 10, y: 20 }),
+            thres: 128
+        });
+        d.push(Sample {
+            features: vec![1.0f32 / 3.0f32, 42.0f32],
+            label: BACKGROUND,
+            image: "b.png".to_string(),
+            bounds: Rect(Point { x: 0, y: 0 }, Point { x: 3, y: 4 }),
+            thres: 7
+        });
+        d
+    }
+
+    #[test]
+    fn csv_roundtrip() {
+        let d = dataset();
+        let mut buf = vec![];
+        d.write_csv(&mut buf).unwrap();
+
+        let loaded = Dataset::read_csv(&mut Cursor::new(buf)).unwrap();
+
+        assert_eq!(loaded.schema.names(), d.schema.names());
+        assert_eq!(loaded.samples, d.samples);
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        let d = dataset();
+        let mut buf = vec![];
+        d.write_binary(&mut buf).unwrap();
+
+        assert_eq!(Dataset::read_binary(&mut Cursor::new(buf)).unwrap(), d);
+    }
+
+    #[test]
+    fn other_versioned_files_are_not_datasets() {
+        let mut buf = vec![];
+        write_versioned("nprs-model", DATASET_VERSION, &dataset(), &mut buf).unwrap();
+
+        match Dataset::read_binary(&mut Cursor::new(buf)) {
+            Err(DatasetError::NotADataset) => {},
+            r => panic!("unexpected result {:?}", r)
+        }
+    }
+
+    #[test]
+    fn csv_errors() {
+        let bad = "image,x1,y1,x2,y2,thres,label,a\na.png,1,2,3,4,5,1\n";
+        match Dataset::read_csv(&mut Cursor::new(bad.as_bytes())) {
+            Err(DatasetError::CsvError { line, .. }) => assert_eq!(line, 2),
+            r => panic!("expected CsvError, got {:?}", r)
+        }
+
+        match Dataset::read_csv(&mut Cursor::new("a,b\n".as_bytes())) {
+            Err(DatasetError::CsvError { line, .. }) => assert_eq!(line, 1),
+            r => panic!("expected CsvError, got {:?}", r)
+        }
+
+        let unterminated = "image,x1,y1,x2,y2,thres,label,a\na.png,1,2,3,4,5,1,0.5\n\"b\nc.png,1,2,3,4,5,1,0.5\n";
+        match Dataset::read_csv(&mut Cursor::new(unterminated.as_bytes())) {
+            Err(DatasetError::CsvError { line, .. }) => assert_eq!(line, 3),
+            r => panic!("expected CsvError, got {:?}", r)
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use std::cmp::{self, Ordering};
+use std::f32;
+use std::fmt::Write;
+
+use rustc_serialize::json;
+
+use super::classifier::{Classifier, is_character, class_name};
+use super::trainer::Trainer;
+use super::rng::XorShift;
+
+/// `counts[actual][predicted]`.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct ConfusionMatrix {
+    pub counts: Vec<Vec<usize>>
+}
+
+impl ConfusionMatrix {
+    pub fn new(num_classes: usize) -> ConfusionMatrix {
+        ConfusionMatrix { counts: vec![vec![0; num_classes]; num_classes] }
+    }
+
+    pub fn add(&mut self, actual: usize, predicted: usize) {
+        self.counts[actual][predicted] += 1;
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().fold(0, |a, r| a + r.iter().fold(0, |a, c| a + c))
+    }
+
+    pub fn accuracy(&self) -> f32 {
+        let correct = (0..self.counts.len()).fold(0, |a, i| a + self.counts[i][i]);
+        ratio(correct, self.total())
+    }
+}
+
+/// Point of the ROC curve of character detection for
+/// `score >= threshold`.
+#[derive(Debug, Copy, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct RocPoint {
+    pub threshold: f32,
+    pub fpr: f32,
+    pub tpr: f32
+}
+
+/// Quality of a classifier on labeled samples. Precision and recall are
+/// computed for characters of any kind against the other classes with
+/// `score >= 0.5`.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Evaluation {
+    pub samples: usize,
+    pub precision: f32,
+    pub recall: f32,
+    pub f1: f32,
+    pub accuracy: f32,
+    pub auc: f32,
+    pub roc: Vec<RocPoint>,
+    pub confusion: ConfusionMatrix,
+    /// AUC of every fold if the evaluation is a cross-validation.
+    pub fold_auc: Vec<f32>
+}
+
+fn ratio(a: usize, b: usize) -> f32 {
+    if b == 0 { 0.0f32 } else { (a as f32) / (b as f32) }
+}
+
+/// ROC curve points from the highest threshold to the lowest one.
+pub fn roc_curve(scores: &[f32], labels: &[usize]) -> Vec<RocPoint> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|a, b| scores[*b].partial_cmp(&scores[*a]).unwrap_or(Ordering::Equal));
+
+    let positives = labels.iter().filter(|l| is_character(**l)).count();
+    let negatives = labels.len() - positives;
+
+    let mut roc = vec![RocPoint { threshold: f32::MAX, fpr: 0.0f32, tpr: 0.0f32 }];
+    let (mut tp, mut fp) = (0, 0);
+    for (k, &i) in order.iter().enumerate() {
+        if is_character(labels[i]) { tp += 1 } else { fp += 1 }
+
+        // one point per distinct score
+        if k + 1 < order.len() && scores[order[k + 1]] == scores[i] {
+            continue;
+        }
+        roc.push(RocPoint { threshold: scores[i], fpr: ratio(fp, negatives), tpr: ratio(tp, positives) });
+    }
+    roc
+}
+
+/// Area under ROC curve using trapezoidal rule.
+pub fn auc(roc: &[RocPoint]) -> f32 {
+    roc.windows(2).fold(0.0f32, |a, w| a + (w[1].fpr - w[0].fpr) * (w[1].tpr + w[0].tpr) * 0.5f32)
+}
+
+impl Evaluation {
+    /// Evaluation of predicted classes and character scores of samples
+    /// with known `labels`.
+    pub fn new(labels: &[usize], predicted: &[usize], scores: &[f32], num_classes: usize) -> Evaluation {
+        let mut confusion = ConfusionMatrix::new(num_classes);
+        let (mut tp, mut fp, mut fn_) = (0, 0, 0);
+
+        for i in 0..labels.len() {
+            confusion.add(labels[i], predicted[i]);
+
+            match (is_character(labels[i]), scores[i] >= 0.5f32) {
+                (true, true) => tp += 1,
+                (false, true) => fp += 1,
+                (true, false) => fn_ += 1,
+                (false, false) => {}
+            }
+        }
+
+        let precision = ratio(tp, tp + fp);
+        let recall = ratio(tp, tp + fn_);
+        let f1 = if precision + recall > 0.0f32 { 2.0f32 * precision * recall / (precision + recall) } else { 0.0f32 };
+        let roc = roc_curve(scores, labels);
+
+        Evaluation {
+            samples: labels.len(),
+            precision: precision,
+            recall: recall,
+            f1: f1,
+            accuracy: confusion.accuracy(),
+            auc: auc(&roc),
+            roc: roc,
+            confusion: confusion,
+            fold_auc: vec![]
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, json::EncoderError> {
+        json::encode(self)
+    }
+
+    /// Human readable report without the ROC curve.
+    pub fn to_text(&self) -> String {
+        let mut s = String::new();
+        writeln!(s, "samples:   {}", self.samples).unwrap();
+        writeln!(s, "precision: {:.4}", self.precision).unwrap();
+        writeln!(s, "recall:    {:.4}", self.recall).unwrap();
+        writeln!(s, "f1:        {:.4}", self.f1).unwrap();
+        writeln!(s, "accuracy:  {:.4}", self.accuracy).unwrap();
+        writeln!(s, "auc:       {:.4}", self.auc).unwrap();
+        if self.fold_auc.len() > 0 {
+            let folds: Vec<String> = self.fold_auc.iter().map(|a| format!("{:.4}", a)).collect();
+            writeln!(s, "fold auc:  {}", folds.join(" ")).unwrap();
+        }
+
+        writeln!(s, "confusion (rows are actual classes):").unwrap();
+        for (class, row) in self.confusion.counts.iter().enumerate() {
+            let cells: Vec<String> = row.iter().map(|c| format!("{:>8}", c)).collect();
+            writeln!(s, "  {:<12}{}", class_name(class), cells.join("")).unwrap();
+        }
+        s
+    }
+}
+
+/// Evaluates trained classifier on held-out samples.
+pub fn evaluate<C: Classifier>(classifier: &C, rows: &[Vec<f32>], labels: &[usize]) -> Evaluation {
+    let predicted: Vec<usize> = classifier.predict_batch(rows).iter().map(|p| p.class).collect();
+    let scores: Vec<f32> = rows.iter().map(|x| classifier.score(x)).collect();
+    let num_classes = cmp::max(classifier.num_classes(), labels.iter().cloned().max().map(|l| l + 1).unwrap_or(0));
+
+    Evaluation::new(labels, &predicted, &scores, num_classes)
+}
+
+/// Assigns every sample to one of `k` folds so that every class is spread
+/// evenly among folds.
+pub fn stratified_folds(labels: &[usize], k: usize, seed: u64) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..labels.len()).collect();
+    XorShift::new(seed).shuffle(&mut order);
+    // sort is stable, so samples stay shuffled within a class
+    order.sort_by_key(|i| labels[*i]);
+
+    let mut folds = vec![0; labels.len()];
+    for (n, i) in order.into_iter().enumerate() {
+        folds[i] = n % k;
+    }
+    folds
+}
+
+/// Predictions of k-fold cross-validation: every sample is predicted by
+/// the classifier trained on the other folds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutOfFold {
+    pub folds: Vec<usize>,
+    pub predicted: Vec<usize>,
+    pub scores: Vec<f32>,
+    pub num_classes: usize
+}
+
+/// Every fold has a sample only if there are at least `k` samples, so
+/// fewer samples panic instead of leaving samples without predictions.
+pub fn out_of_fold<T: Trainer>(trainer: &T, rows: &[Vec<f32>], labels: &[usize], k: usize, seed: u64) -> OutOfFold {
+    debug_assert!(k >= 2);
+    assert!(rows.len() >= k, "{} samples can't be split into {} folds", rows.len(), k);
+
+    let folds = stratified_folds(labels, k, seed);
+    let n = rows.len();
+    let mut predicted = vec![0; n];
+    let mut scores = vec![0.0f32; n];
+    let mut num_classes = labels.iter().cloned().max().map(|l| l + 1).unwrap_or(0);
+
+    for fold in 0..k {
+        let (train, test): (Vec<usize>, Vec<usize>) = (0..n).partition(|i| folds[*i] != fold);
+
+        let train_rows: Vec<Vec<f32>> = train.iter().map(|i| rows[*i].clone()).collect();
+        let train_labels: Vec<usize> = train.iter().map(|i| labels[*i]).collect();
+        let classifier = trainer.train(&train_rows, &train_labels);
+        num_classes = cmp::max(num_classes, classifier.num_classes());
+
+        for &i in test.iter() {
+            predicted[i] = classifier.predict(&rows[i]).class;
+            scores[i] = classifier.score(&rows[i]);
+        }
+    }
+
+    OutOfFold {
+        folds: folds,
+        predicted: predicted,
+        scores: scores,
+        num_classes: num_classes
+    }
+}
+
+/// k-fold cross-validation, metrics are computed over all the out-of-fold
+/// predictions.
+pub fn cross_validate<T: Trainer>(trainer: &T, rows: &[Vec<f32>], labels: &[usize], k: usize, seed: u64) -> Evaluation {
+    let oof = out_of_fold(trainer, rows, labels, k, seed);
+
+    let fold_auc = (0..k)
+        .map(|fold| {
+            let test: Vec<usize> = (0..labels.len()).filter(|i| oof.folds[*i] == fold).collect();
+            let fold_scores: Vec<f32> = test.iter().map(|i| oof.scores[*i]).collect();
+            let fold_labels: Vec<usize> = test.iter().map(|i| labels[*i]).collect();
+            auc(&roc_curve(&fold_scores, &fold_labels))
+        })
+        .collect();
+
+    let mut res = Evaluation::new(labels, &oof.predicted, &oof.scores, oof.num_classes);
+    res.fold_auc = fold_auc;
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ml::trainer::{ModelTrainer, Algorithm};
+    use ml::normalize::StepSpec;
+
+    #[test]
+    fn perfect_and_inverted_auc() {
+        let labels = vec![0, 0, 1, 1];
+        assert_eq!(auc(&roc_curve(&[0.1f32, 0.2f32, 0.8f32, 0.9f32], &labels)), 1.0f32);
+        assert_eq!(auc(&roc_curve(&[0.9f32, 0.8f32, 0.2f32, 0.1f32], &labels)), 0.0f32);
+        // all scores tied
+        assert_eq!(auc(&roc_curve(&[0.5f32; 4], &labels)), 0.5f32);
+    }
+
+    #[test]
+    fn precision_recall() {
+        let labels = vec![1, 1, 1, 0, 0];
+        let scores = vec![0.9f32, 0.7f32, 0.3f32, 0.6f32, 0.1f32];
+        let predicted: Vec<usize> = scores.iter().map(|s| if *s >= 0.5f32 { 1 } else { 0 }).collect();
+
+        let e = Evaluation::new(&labels, &predicted, &scores, 2);
+
+        assert_eq!(e.precision, 2.0f32 / 3.0f32);
+        assert_eq!(e.recall, 2.0f32 / 3.0f32);
+        assert_eq!(e.confusion.counts, vec![vec![1, 1], vec![1, 2]]);
+        assert_eq!(e.accuracy, 3.0f32 / 5.0f32);
+    }
+
+    #[test]
+    fn stratified() {
+        let labels = vec![0, 0, 0, 0, 1, 1, 1, 1];
+        let folds = stratified_folds(&labels, 2, 3);
+
+        for fold in 0..2 {
+            let positives = (0..8).filter(|i| folds[*i] == fold && labels[*i] == 1).count();
+            assert_eq!(positives, 2);
+        }
+    }
+
+    #[test]
+    fn cross_validate_separable() {
+        let rows: Vec<Vec<f32>> = (0..40).map(|i| vec![i as f32]).collect();
+        let labels: Vec<usize> = (0..40).map(|i| if i >= 20 { 1 } else { 0 }).collect();
+        let trainer = ModelTrainer::new(vec![StepSpec::Standardize], Algorithm::from_name("logistic").unwrap());
+
+        let e = cross_validate(&trainer, &rows, &labels, 4, 1);
+
+        assert_eq!(e.samples, 40);
+        assert_eq!(e.fold_auc.len(), 4);
+        assert!(e.auc > 0.95f32);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fewer_samples_than_folds() {
+        let rows = vec![vec![0.0f32], vec![1.0f32], vec![2.0f32]];
+        let trainer = ModelTrainer::new(vec![StepSpec::Standardize], Algorithm::from_name("logistic").unwrap());
+
+        out_of_fold(&trainer, &rows, &[0, 1, 1], 4, 1);
+    }
+}
@@ -0,0 +1,254 @@
+use std::cmp::Ordering;
+use std::fmt::Write;
+
+use super::classifier::{Classifier, character_probability};
+use super::model::{Model, ClassifierModel};
+
+/// Units of contributions of an explanation.
+#[derive(Debug, Copy, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Units {
+    /// Log-odds of the character probability.
+    Logit,
+    /// Character probability, or any other score in `0..1`.
+    Probability
+}
+
+/// Part of a decision attributed to a single feature.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct FeatureContribution {
+    pub name: String,
+    pub value: f32,
+    /// Value after normalization, as seen by the classifier.
+    pub normalized: f32,
+    pub contribution: f32
+}
+
+/// Why a region got its score. `base` plus all contributions is the raw
+/// (uncalibrated) output of the classifier in `units`, `score` is the
+/// final calibrated score of the region.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Explanation {
+    pub score: f32,
+    pub base: f32,
+    pub units: Units,
+    /// Whether contributions are exact, as for linear and boosted models,
+    /// or estimated by occlusion, as for trees and networks.
+    pub exact: bool,
+    pub features: Vec<FeatureContribution>
+}
+
+/// Change of `score` of `x` when a feature is replaced by its value in
+/// `reference`, for every feature.
+fn occlusion<F: Fn(&[f32]) -> f32>(score: &F, x: &[f32], reference: &[f32]) -> Vec<f32> {
+    let output = score(x);
+    let mut occluded = x.to_vec();
+    let mut contributions = vec![];
+    for f in 0..x.len() {
+        occluded[f] = reference[f];
+        contributions.push(output - score(&occluded));
+        occluded[f] = x[f];
+    }
+    contributions
+}
+
+/// Contributions of normalized features `x` to the output of `classifier`
+/// with the rest attributed to `base`, returns `(base, contributions, units, exact)`.
+/// Approximate contributions are relative to `means` of normalized features.
+fn contributions(classifier: &ClassifierModel, x: &[f32], means: &[f32]) -> (f32, Vec<f32>, Units, bool) {
+    match *classifier {
+        ClassifierModel::Logistic(ref c) => {
+            let contributions = c.weights().iter().zip(x.iter()).map(|(w, v)| w * v).collect();
+            (c.bias(), contributions, Units::Logit, true)
+        },
+        ClassifierModel::AdaBoost(ref c) => {
+            // probability is `sigmoid(2 * margin)`
+            let contributions = x.iter().enumerate().map(|(f, v)| 2.0f32 * c.contribution(f, *v)).collect();
+            (0.0f32, contributions, Units::Logit, true)
+        },
+        ClassifierModel::RandomForest(_) | ClassifierModel::Mlp(_) => {
+            let probability = |x: &[f32]| -> f32 {
+                let mut p = vec![];
+                classifier.probabilities(x, &mut p);
+                character_probability(&p)
+            };
+
+            // change of the output when the feature is replaced by its
+            // training mean
+            let contributions = occlusion(&probability, x, means);
+            let base = contributions.iter().fold(probability(x), |a, c| a - c);
+            (base, contributions, Units::Probability, false)
+        }
+    }
+}
+
+/// Explains the score of a region with raw feature vector `x`.
+pub fn explain(model: &Model, x: &[f32]) -> Explanation {
+    let normalizer = &model.classifier.normalizer;
+    let mut nx = x.to_vec();
+    normalizer.apply(&mut nx);
+
+    let (base, contributions, units, exact) = contributions(&model.classifier.classifier, &nx, normalizer.means());
+    let names = model.schema.names();
+
+    Explanation {
+        score: model.score(x),
+        base: base,
+        units: units,
+        exact: exact,
+        features: (0..x.len())
+            .map(|f| FeatureContribution {
+                name: names.get(f).map(|n| n.to_string()).unwrap_or_else(|| format!("feature_{}", f)),
+                value: x[f],
+                normalized: nx[f],
+                contribution: contributions[f]
+            })
+            .collect()
+    }
+}
+
+/// Explains `score` of feature vector `x` named `names` by occlusion: a
+/// feature contributes the change of the score when it's replaced by its
+/// value in `reference`. Features aren't normalized.
+pub fn explain_by_occlusion<F: Fn(&[f32]) -> f32>(score: F, names: &[&str], x: &[f32], reference: &[f32]) -> Explanation {
+    let contributions = occlusion(&score, x, reference);
+    let output = score(x);
+
+    Explanation {
+        score: output,
+        base: contributions.iter().fold(output, |a, c| a - c),
+        units: Units::Probability,
+        exact: false,
+        features: (0..x.len())
+            .map(|f| FeatureContribution {
+                name: names.get(f).map(|n| n.to_string()).unwrap_or_else(|| format!("feature_{}", f)),
+                value: x[f],
+                normalized: x[f],
+                contribution: contributions[f]
+            })
+            .collect()
+    }
+}
+
+impl Explanation {
+    /// Raw classifier output, `base` plus all contributions.
+    pub fn output(&self) -> f32 {
+        self.features.iter().fold(self.base, |a, f| a + f.contribution)
+    }
+
+    /// Human readable report, the most influential features first.
+    pub fn to_text(&self) -> String {
+        let mut features: Vec<&FeatureContribution> = self.features.iter().collect();
+        features.sort_by(|a, b| b.contribution.abs().partial_cmp(&a.contribution.abs()).unwrap_or(Ordering::Equal));
+
+        let mut s = String::new();
+        writeln!(s, "score: {:.4}", self.score).unwrap();
+        writeln!(
+            s, "base:  {:.4} ({:?}{})",
+            self.base, self.units, if self.exact { "" } else { ", approximate" }
+        ).unwrap();
+        for f in features {
+            writeln!(s, "  {:<32} {:>10.4} {:>10.4} {:>+10.4}", f.name, f.value, f.normalized, f.contribution).unwrap();
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use extract::cser::feature::{Schema, AspectRatio, NumHoles};
+    use ml::adaboost::{AdaBoost, Stump};
+    use ml::calibration::Calibration;
+    use ml::classifier::{Classifier, Normalized};
+    use ml::logistic::{LogisticRegression, sigmoid};
+    use ml::mlp::Mlp;
+    use ml::model::{Model, ClassifierModel};
+    use ml::normalize::{Normalizer, StepSpec};
+    use ml::trainer::{Trainer, ModelTrainer, Algorithm};
+    use super::*;
+
+    fn model(classifier: ClassifierModel) -> Model {
+        let rows = vec![vec![1.0f32, 0.0f32], vec![3.0f32, 2.0f32]];
+        Model {
+            features: "aspect_ratio,num_holes".to_string(),
+            second_stage: String::new(),
+            schema: Schema::of::<(AspectRatio, NumHoles)>(),
+            classifier: Normalized {
+                normalizer: Normalizer::fit(&[StepSpec::Standardize], &rows),
+                classifier: classifier
+            },
+            calibration: Calibration::Identity
+        }
+    }
+
+    #[test]
+    fn linear_is_exact() {
+        let m = model(ClassifierModel::Logistic(LogisticRegression::new(vec![1.0f32, -2.0f32], 0.5f32)));
+        let e = explain(&m, &[3.0f32, 0.0f32]);
+
+        assert!(e.exact);
+        assert_eq!(e.units, Units::Logit);
+        assert_eq!(e.features[0].normalized, 1.0f32);
+        assert_eq!(e.features[1].normalized, -1.0f32);
+        assert_eq!(e.features[0].contribution, 1.0f32);
+        assert_eq!(e.features[1].contribution, 2.0f32);
+        assert!((sigmoid(e.output()) - e.score).abs() < 1e-6);
+    }
+
+    #[test]
+    fn boosted_is_exact() {
+        let m = model(ClassifierModel::AdaBoost(AdaBoost::from_stumps(2, vec![
+            Stump { feature: 0, threshold: 0.0f32, left: -1.0f32, right: 1.0f32 },
+            Stump { feature: 1, threshold: 0.5f32, left: 0.5f32, right: -0.5f32 },
+        ])));
+        let e = explain(&m, &[3.0f32, 0.0f32]);
+
+        assert!(e.exact);
+        assert_eq!(e.features[0].contribution, 2.0f32);
+        assert_eq!(e.features[1].contribution, 1.0f32);
+        assert!((sigmoid(e.output()) - e.score).abs() < 1e-6);
+    }
+
+    #[test]
+    fn forest_is_approximate() {
+        // only the first feature matters
+        let rows: Vec<Vec<f32>> = (0..40).map(|i| vec![(i as f32) / 40.0f32, (i % 5) as f32]).collect();
+        let labels: Vec<usize> = (0..40).map(|i| if i >= 30 { 1 } else { 0 }).collect();
+        let trainer = ModelTrainer::new(vec![StepSpec::Standardize], Algorithm::from_name("forest").unwrap());
+        let trained = trainer.train(&rows, &labels);
+
+        let mut m = model(trained.classifier.clone());
+        m.classifier = trained;
+        let e = explain(&m, &[0.9f32, 3.0f32]);
+
+        assert!(!e.exact);
+        assert_eq!(e.units, Units::Probability);
+        assert!((e.output() - m.score(&[0.9f32, 3.0f32])).abs() < 1e-5);
+        assert!(e.features[0].contribution > e.features[1].contribution.abs());
+    }
+
+    #[test]
+    fn occlusion_is_relative_to_training_means() {
+        // min-max normalized training means are 0.4 and 0.5
+        let rows = vec![vec![0.0f32, 0.0f32], vec![1.0f32, 1.0f32], vec![5.0f32, 2.0f32]];
+        let mut m = model(ClassifierModel::Mlp(Mlp::new(&[2, 4, 2], 3)));
+        m.classifier.normalizer = Normalizer::fit(&[StepSpec::MinMax], &rows);
+
+        let e = explain(&m, &[2.0f32, 1.0f32]);
+
+        for f in e.features.iter() {
+            assert!(f.contribution.abs() < 1e-5, "{:?}", f);
+        }
+        assert!((e.base - e.score).abs() < 1e-5);
+    }
+
+    #[test]
+    fn occlusion_of_any_score() {
+        let score = |x: &[f32]| x[0] / (1.0f32 + x[1]);
+        let e = explain_by_occlusion(score, &["a", "b"], &[0.8f32, 1.0f32], &[0.8f32, 0.0f32]);
+
+        assert_eq!(e.score, 0.4f32);
+        assert_eq!(e.features[0].contribution, 0.0f32);
+        assert_eq!(e.features[1].contribution, -0.4f32);
+        assert_eq!(e.features[1].name, "b");
+    }
+}
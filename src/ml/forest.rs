@@ -0,0 +1,292 @@
+use std::cmp::{self, Ordering};
+
+use super::classifier::Classifier;
+use super::rng::XorShift;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ForestParams {
+    pub trees: usize,
+    pub max_depth: usize,
+    /// Nodes with fewer samples are not split.
+    pub min_samples_split: usize,
+    pub min_samples_leaf: usize,
+    /// Number of features tried at every split, `sqrt` of the number of
+    /// features if not set.
+    pub features_per_split: Option<usize>,
+    pub seed: u64
+}
+
+impl Default for ForestParams {
+    fn default() -> ForestParams {
+        ForestParams {
+            trees: 50,
+            max_depth: 12,
+            min_samples_split: 4,
+            min_samples_leaf: 1,
+            features_per_split: None,
+            seed: 1
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+enum Node {
+    /// Samples with `x[feature] < threshold` go to the `left` node.
+    Split { feature: usize, threshold: f32, left: usize, right: usize },
+    Leaf { probabilities: Vec<f32> }
+}
+
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+struct Tree {
+    nodes: Vec<Node>
+}
+
+impl Tree {
+    fn leaf<'a>(&'a self, x: &[f32]) -> &'a [f32] {
+        let mut i = 0;
+        loop {
+            match self.nodes[i] {
+                Node::Split { feature, threshold, left, right } => {
+                    i = if x[feature] < threshold { left } else { right };
+                },
+                Node::Leaf { ref probabilities } => return &probabilities[..]
+            }
+        }
+    }
+}
+
+fn gini(counts: &[usize], n: usize) -> f32 {
+    if n == 0 {
+        return 0.0f32;
+    }
+    let n = n as f32;
+    1.0f32 - counts.iter().fold(0.0f32, |a, c| a + ((*c as f32) / n) * ((*c as f32) / n))
+}
+
+struct Builder<'a> {
+    rows: &'a [Vec<f32>],
+    labels: &'a [usize],
+    num_classes: usize,
+    params: &'a ForestParams,
+    features_per_split: usize,
+    rng: XorShift,
+    /// Total weighted impurity decrease per feature.
+    importance: Vec<f32>
+}
+
+impl<'a> Builder<'a> {
+    fn counts(&self, idx: &[usize]) -> Vec<usize> {
+        let mut counts = vec![0; self.num_classes];
+        for i in idx {
+            counts[self.labels[*i]] += 1;
+        }
+        counts
+    }
+
+    fn build(&mut self, idx: Vec<usize>) -> Tree {
+        let mut nodes = vec![];
+        self.build_node(idx, 0, &mut nodes);
+        Tree { nodes: nodes }
+    }
+
+    fn build_node(&mut self, mut idx: Vec<usize>, depth: usize, nodes: &mut Vec<Node>) -> usize {
+        let counts = self.counts(&idx);
+        let n = idx.len();
+        let node = nodes.len();
+
+        let pure = counts.iter().filter(|c| **c > 0).count() <= 1;
+        let split = if pure || depth >= self.params.max_depth || n < self.params.min_samples_split {
+            None
+        } else {
+            self.best_split(&mut idx, &counts)
+        };
+
+        match split {
+            None => {
+                let probabilities = counts.iter().map(|c| (*c as f32) / (cmp::max(n, 1) as f32)).collect();
+                nodes.push(Node::Leaf { probabilities: probabilities });
+            },
+            Some((feature, threshold, decrease)) => {
+                self.importance[feature] += decrease;
+
+                let rows = self.rows;
+                let (l, r): (Vec<usize>, Vec<usize>) = idx.into_iter().partition(|i| rows[*i][feature] < threshold);
+
+                nodes.push(Node::Leaf { probabilities: vec![] });
+                let left = self.build_node(l, depth + 1, nodes);
+                let right = self.build_node(r, depth + 1, nodes);
+                nodes[node] = Node::Split { feature: feature, threshold: threshold, left: left, right: right };
+            }
+        }
+
+        node
+    }
+
+    /// Best `(feature, threshold, weighted impurity decrease)` among a random
+    /// subset of features.
+    fn best_split(&mut self, idx: &mut [usize], counts: &[usize]) -> Option<(usize, f32, f32)> {
+        let n = idx.len();
+        let node_impurity = (n as f32) * gini(counts, n);
+        let min_leaf = cmp::max(self.params.min_samples_leaf, 1);
+
+        let mut features: Vec<usize> = (0..self.importance.len()).collect();
+        self.rng.shuffle(&mut features);
+
+        let mut best: Option<(usize, f32, f32)> = None;
+        for &f in features.iter().take(self.features_per_split) {
+            let rows = self.rows;
+            idx.sort_by(|a, b| rows[*a][f].partial_cmp(&rows[*b][f]).unwrap_or(Ordering::Equal));
+
+            let mut left = vec![0; self.num_classes];
+            let mut right = counts.to_vec();
+
+            for k in 0..(n - 1) {
+                let label = self.labels[idx[k]];
+                left[label] += 1;
+                right[label] -= 1;
+
+                let (v, next) = (rows[idx[k]][f], rows[idx[k + 1]][f]);
+                let (nl, nr) = (k + 1, n - k - 1);
+                if v == next || nl < min_leaf || nr < min_leaf {
+                    continue;
+                }
+
+                let impurity = (nl as f32) * gini(&left, nl) + (nr as f32) * gini(&right, nr);
+                let decrease = node_impurity - impurity;
+                if decrease > 0.0f32 && best.map(|b| decrease > b.2).unwrap_or(true) {
+                    best = Some((f, 0.5f32 * (v + next), decrease));
+                }
+            }
+        }
+
+        best
+    }
+}
+
+/// Random forest of Gini decision trees trained on bootstrap samples.
+///
+/// Works on any labeled feature vectors with any number of classes, so the
+/// same implementation scores both character regions and plate hypotheses.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct RandomForest {
+    num_classes: usize,
+    trees: Vec<Tree>,
+    importance: Vec<f32>
+}
+
+impl RandomForest {
+    pub fn train(rows: &[Vec<f32>], labels: &[usize], params: &ForestParams) -> RandomForest {
+        debug_assert!(rows.len() == labels.len());
+
+        let n = rows.len();
+        let num_features = rows.first().map(|r| r.len()).unwrap_or(0);
+        let num_classes = cmp::max(labels.iter().cloned().max().map(|l| l + 1).unwrap_or(0), 2);
+        let features_per_split = params.features_per_split
+            .unwrap_or(((num_features as f32).sqrt().round()) as usize);
+
+        let mut builder = Builder {
+            rows: rows,
+            labels: labels,
+            num_classes: num_classes,
+            params: params,
+            features_per_split: cmp::min(cmp::max(features_per_split, 1), num_features),
+            rng: XorShift::new(params.seed),
+            importance: vec![0.0f32; num_features]
+        };
+
+        let mut trees = vec![];
+        if n > 0 {
+            for _ in 0..params.trees {
+                let bootstrap: Vec<usize> = (0..n).map(|_| builder.rng.below(n)).collect();
+                trees.push(builder.build(bootstrap));
+            }
+        }
+
+        let total = builder.importance.iter().fold(0.0f32, |a, v| a + v);
+        let importance = builder.importance.iter()
+            .map(|v| if total > 0.0f32 { v / total } else { 0.0f32 })
+            .collect();
+
+        RandomForest {
+            num_classes: num_classes,
+            trees: trees,
+            importance: importance
+        }
+    }
+
+    pub fn num_trees(&self) -> usize {
+        self.trees.len()
+    }
+
+    /// Mean decrease of Gini impurity contributed by every feature,
+    /// normalized to sum to one.
+    pub fn feature_importance<'a>(&'a self) -> &'a [f32] {
+        &self.importance[..]
+    }
+}
+
+impl Classifier for RandomForest {
+    fn num_classes(&self) -> usize {
+        self.num_classes
+    }
+
+    fn probabilities(&self, x: &[f32], out: &mut Vec<f32>) {
+        out.clear();
+        out.resize(self.num_classes, 0.0f32);
+
+        for t in self.trees.iter() {
+            for (o, p) in out.iter_mut().zip(t.leaf(x).iter()) {
+                *o += *p;
+            }
+        }
+
+        let n = cmp::max(self.trees.len(), 1) as f32;
+        for o in out.iter_mut() {
+            *o /= n;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ml::classifier::Classifier;
+    use ml::rng::XorShift;
+
+    #[test]
+    fn xor_with_noise_feature() {
+        let mut rng = XorShift::new(7);
+        let mut rows = vec![];
+        let mut labels = vec![];
+        for _ in 0..400 {
+            let (a, b) = (rng.uniform(), rng.uniform());
+            rows.push(vec![a, b, rng.uniform()]);
+            labels.push(if (a < 0.5f32) != (b < 0.5f32) { 1 } else { 0 });
+        }
+
+        let params = ForestParams { features_per_split: Some(2), ..ForestParams::default() };
+        let forest = RandomForest::train(&rows, &labels, &params);
+
+        for &(x, expected) in [([0.2f32, 0.2f32, 0.5f32], 0), ([0.2f32, 0.8f32, 0.5f32], 1),
+                               ([0.8f32, 0.2f32, 0.5f32], 1), ([0.8f32, 0.8f32, 0.5f32], 0)].iter() {
+            assert_eq!(forest.predict(&x).class, expected);
+        }
+
+        let importance = forest.feature_importance();
+        assert!(importance[2] < importance[0] && importance[2] < importance[1]);
+        assert!((importance.iter().fold(0.0f32, |a, v| a + v) - 1.0f32).abs() < 1e-4);
+    }
+
+    #[test]
+    fn multi_class() {
+        let rows: Vec<Vec<f32>> = (0..30).map(|i| vec![i as f32]).collect();
+        let labels: Vec<usize> = (0..30).map(|i| i / 10).collect();
+
+        let forest = RandomForest::train(&rows, &labels, &ForestParams::default());
+
+        assert_eq!(forest.num_classes(), 3);
+        assert_eq!(forest.predict(&[5.0f32]).class, 0);
+        assert_eq!(forest.predict(&[15.0f32]).class, 1);
+        assert_eq!(forest.predict(&[25.0f32]).class, 2);
+    }
+}
@@ -0,0 +1,221 @@
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use rustc_serialize::json;
+
+use structures::{Point, Rect};
+use extract::ExtremalRegion;
+use super::classifier::{BACKGROUND, CHARACTER, DIGIT, LETTER, BORDER};
+use super::dataset::Sample;
+
+/// Box of a single character or plate, in pixels. `text` of a character
+/// box is the character itself, when known.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct BoxAnnotation {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub text: Option<String>
+}
+
+impl BoxAnnotation {
+    pub fn rect(&self) -> Rect {
+        Rect(
+            Point { x: self.x, y: self.y },
+            Point { x: self.x + self.width - 1, y: self.y + self.height - 1 }
+        )
+    }
+}
+
+/// Class of a character box by its text: digit, letter or a character of
+/// unknown kind.
+pub fn character_class(text: Option<&str>) -> usize {
+    match text.and_then(|t| t.chars().next()) {
+        Some(c) if c.is_numeric() => DIGIT,
+        Some(c) if c.is_alphabetic() => LETTER,
+        _ => CHARACTER
+    }
+}
+
+/// Ground truth of a single image. `image` path is relative to the
+/// manifest file. Regions matching `plates` boxes are the plate border.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Annotation {
+    pub image: String,
+    pub characters: Vec<BoxAnnotation>,
+    pub plates: Option<Vec<BoxAnnotation>>
+}
+
+impl Annotation {
+    /// Annotated boxes with their region classes.
+    pub fn truth(&self) -> Vec<(Rect, usize)> {
+        let mut res: Vec<(Rect, usize)> = self.characters.iter()
+            .map(|c| (c.rect(), character_class(c.text.as_ref().map(|t| &t[..]))))
+            .collect();
+        if let Some(ref plates) = self.plates {
+            res.extend(plates.iter().map(|p| (p.rect(), BORDER)));
+        }
+        res
+    }
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    IoError(io::Error),
+    DecodeError(json::DecoderError)
+}
+
+/// Loads JSON array of annotations, e.g.
+/// `[{ "image": "001.jpg", "characters": [{ "x": 10, "y": 5, "width": 8, "height": 14, "text": "7" }],
+/// "plates": [{ "x": 2, "y": 1, "width": 60, "height": 20 }] }]`.
+/// `text` and `plates` are optional, without them regions are labeled just
+/// as characters and background. Image paths are resolved relative to
+/// manifest directory.
+pub fn load_manifest(path: &str) -> Result<Vec<Annotation>, ManifestError> {
+    let mut f = try!(File::open(path).map_err(|e| ManifestError::IoError(e)));
+    let mut s = String::new();
+    try!(f.read_to_string(&mut s).map_err(|e| ManifestError::IoError(e)));
+
+    let annotations: Vec<Annotation> = try!(json::decode(&s).map_err(|e| ManifestError::DecodeError(e)));
+
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    Ok(annotations.into_iter()
+        .map(|a| Annotation {
+            image: dir.join(&a.image).to_string_lossy().into_owned(),
+            characters: a.characters,
+            plates: a.plates
+        })
+        .collect())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Labeling {
+    /// Regions overlapping an annotated box at least this much get its class.
+    pub positive_iou: f32,
+    /// Regions overlapping all annotated boxes less than this are background.
+    /// Regions in between are ambiguous and skipped.
+    pub negative_iou: f32,
+    pub min_size: (i32, i32),
+    pub max_size: (i32, i32),
+    /// Upper bound of the size of plate border regions, which are much
+    /// larger than characters.
+    pub max_plate_size: (i32, i32)
+}
+
+impl Default for Labeling {
+    fn default() -> Labeling {
+        Labeling {
+            positive_iou: 0.5f32,
+            negative_iou: 0.2f32,
+            min_size: (4, 3),
+            max_size: (150, 150),
+            max_plate_size: (600, 200)
+        }
+    }
+}
+
+impl Labeling {
+    /// Class of the best overlapping box of `truth`, `None` if ambiguous.
+    pub fn label(&self, bounds: Rect, truth: &[(Rect, usize)]) -> Option<usize> {
+        let (best, class) = truth.iter()
+            .map(|t| (bounds.iou(t.0), t.1))
+            .fold((0.0f32, BACKGROUND), |a, b| if b.0 > a.0 { b } else { a });
+        if best >= self.positive_iou {
+            Some(class)
+        } else if best < self.negative_iou {
+            Some(BACKGROUND)
+        } else {
+            None
+        }
+    }
+
+    fn fits(&self, bounds: Rect, class: usize) -> bool {
+        let max_size = if class == BORDER { self.max_plate_size } else { self.max_size };
+        self.min_size.0 <= bounds.width() && self.min_size.1 <= bounds.height() &&
+        max_size.0 >= bounds.width() && max_size.1 >= bounds.height()
+    }
+
+    /// Labels detected regions of image `image` by their overlap with
+    /// ground truth boxes.
+    pub fn samples<R: ExtremalRegion>(&self, image: &str, regions: &[R], truth: &[(Rect, usize)]) -> Vec<Sample> {
+        self.samples_with(image, regions, truth, |_, _| {})
+    }
+
+    /// `samples` with features of every region followed by the ones that
+    /// `extra` writes, e.g. its second-stage features.
+    pub fn samples_with<R, F>(&self, image: &str, regions: &[R], truth: &[(Rect, usize)], extra: F) -> Vec<Sample>
+        where R: ExtremalRegion, F: Fn(&R, &mut Vec<f32>) {
+        let mut res = vec![];
+        for r in regions.iter() {
+            let label = self.label(r.bounds(), truth);
+            if let Some(label) = label.and_then(|l| if self.fits(r.bounds(), l) { Some(l) } else { None }) {
+                let mut features = vec![];
+                r.feature_vec(&mut features);
+                extra(r, &mut features);
+                res.push(Sample {
+                    features: features,
+                    label: label,
+                    image: image.to_string(),
+                    bounds: r.bounds(),
+                    thres: r.threshold()
+                });
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use structures::{Point, Rect};
+    use ml::classifier::{BACKGROUND, CHARACTER, DIGIT, LETTER, BORDER};
+    use super::*;
+
+    #[test]
+    fn label_by_iou() {
+        let labeling = Labeling::default();
+        let truth = vec![(Rect(Point { x: 0, y: 0 }, Point { x: 9, y: 9 }), CHARACTER)];
+
+        let exact = Rect(Point { x: 0, y: 0 }, Point { x: 9, y: 9 });
+        let partial = Rect(Point { x: 0, y: 0 }, Point { x: 9, y: 3 });
+        let outside = Rect(Point { x: 20, y: 0 }, Point { x: 29, y: 9 });
+
+        assert_eq!(labeling.label(exact, &truth), Some(CHARACTER));
+        assert_eq!(labeling.label(partial, &truth), None);
+        assert_eq!(labeling.label(outside, &truth), Some(BACKGROUND));
+    }
+
+    #[test]
+    fn label_by_class_of_best_box() {
+        let annotation = Annotation {
+            image: "001.jpg".to_string(),
+            characters: vec![
+                BoxAnnotation { x: 2, y: 2, width: 8, height: 14, text: Some("7".to_string()) },
+                BoxAnnotation { x: 12, y: 2, width: 8, height: 14, text: Some("B".to_string()) },
+                BoxAnnotation { x: 22, y: 2, width: 8, height: 14, text: None },
+            ],
+            plates: Some(vec![BoxAnnotation { x: 0, y: 0, width: 32, height: 18, text: None }])
+        };
+        let truth = annotation.truth();
+        let labeling = Labeling::default();
+
+        let rect = |x: i32, y: i32, w: i32, h: i32| Rect(Point { x: x, y: y }, Point { x: x + w - 1, y: y + h - 1 });
+        assert_eq!(labeling.label(rect(2, 2, 8, 14), &truth), Some(DIGIT));
+        assert_eq!(labeling.label(rect(12, 3, 8, 13), &truth), Some(LETTER));
+        assert_eq!(labeling.label(rect(22, 2, 8, 14), &truth), Some(CHARACTER));
+        assert_eq!(labeling.label(rect(0, 0, 32, 18), &truth), Some(BORDER));
+    }
+
+    #[test]
+    fn plates_have_own_size_limit() {
+        let labeling = Labeling::default();
+        let plate = Rect(Point { x: 0, y: 0 }, Point { x: 259, y: 59 });
+
+        assert!(labeling.fits(plate, BORDER));
+        assert!(!labeling.fits(plate, BACKGROUND));
+        assert!(!labeling.fits(plate, CHARACTER));
+    }
+}
@@ -0,0 +1,161 @@
+use super::classifier::{Classifier, BACKGROUND, CHARACTER, is_character};
+use super::online::OnlineUpdate;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LogisticParams {
+    pub learning_rate: f32,
+    pub iterations: usize,
+    pub l2: f32
+}
+
+impl Default for LogisticParams {
+    fn default() -> LogisticParams {
+        LogisticParams {
+            learning_rate: 0.1f32,
+            iterations: 1000,
+            l2: 0.0f32
+        }
+    }
+}
+
+/// Binary logistic regression, `p(character) = sigmoid(w * x + b)`.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct LogisticRegression {
+    weights: Vec<f32>,
+    bias: f32
+}
+
+impl LogisticRegression {
+    pub fn new(weights: Vec<f32>, bias: f32) -> LogisticRegression {
+        LogisticRegression { weights: weights, bias: bias }
+    }
+
+    /// Trains with full batch gradient descent. Characters of every
+    /// kind are positive, background and border are negative.
+    pub fn train(rows: &[Vec<f32>], labels: &[usize], params: &LogisticParams) -> LogisticRegression {
+        debug_assert!(rows.len() == labels.len());
+
+        let n = rows.first().map(|r| r.len()).unwrap_or(0);
+        let mut model = LogisticRegression::new(vec![0.0f32; n], 0.0f32);
+        let m = rows.len() as f32;
+
+        let mut grad = vec![0.0f32; n];
+        for _ in 0..params.iterations {
+            for g in grad.iter_mut() {
+                *g = 0.0f32;
+            }
+            let mut grad_bias = 0.0f32;
+
+            for (x, label) in rows.iter().zip(labels.iter()) {
+                let y = if is_character(*label) { 1.0f32 } else { 0.0f32 };
+                let err = model.probability(x) - y;
+                for (g, xi) in grad.iter_mut().zip(x.iter()) {
+                    *g += err * xi;
+                }
+                grad_bias += err;
+            }
+
+            for (w, g) in model.weights.iter_mut().zip(grad.iter()) {
+                *w -= params.learning_rate * (g / m + params.l2 * *w);
+            }
+            model.bias -= params.learning_rate * grad_bias / m;
+        }
+
+        model
+    }
+
+    pub fn weights<'a>(&'a self) -> &'a [f32] {
+        &self.weights[..]
+    }
+
+    pub fn bias(&self) -> f32 {
+        self.bias
+    }
+
+    pub fn margin(&self, x: &[f32]) -> f32 {
+        debug_assert!(x.len() == self.weights.len());
+        self.weights.iter().zip(x.iter()).fold(self.bias, |a, (w, xi)| a + w * xi)
+    }
+
+    /// Probability of positive class.
+    pub fn probability(&self, x: &[f32]) -> f32 {
+        sigmoid(self.margin(x))
+    }
+}
+
+pub fn sigmoid(z: f32) -> f32 {
+    1.0f32 / (1.0f32 + (-z).exp())
+}
+
+impl Classifier for LogisticRegression {
+    fn num_classes(&self) -> usize {
+        2
+    }
+
+    fn probabilities(&self, x: &[f32], out: &mut Vec<f32>) {
+        let p = self.probability(x);
+        out.clear();
+        out.push(1.0f32 - p);
+        out.push(p);
+    }
+}
+
+impl OnlineUpdate for LogisticRegression {
+    /// Single stochastic gradient descent step. The model is binary, so
+    /// labels other than `BACKGROUND` and `CHARACTER` can't be learned.
+    fn update(&mut self, x: &[f32], label: usize, learning_rate: f32) -> bool {
+        if label != BACKGROUND && label != CHARACTER {
+            return false;
+        }
+        let y = if label == CHARACTER { 1.0f32 } else { 0.0f32 };
+        let err = self.probability(x) - y;
+        for (w, xi) in self.weights.iter_mut().zip(x.iter()) {
+            *w -= learning_rate * err * xi;
+        }
+        self.bias -= learning_rate * err;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ml::classifier::{Classifier, BACKGROUND, CHARACTER, DIGIT, LETTER, BORDER};
+    use ml::online::OnlineUpdate;
+
+    #[test]
+    fn separable_1d() {
+        let rows = vec![vec![1.0f32], vec![3.0f32], vec![5.0f32], vec![7.0f32]];
+        let labels = vec![0, 0, 1, 1];
+
+        let model = LogisticRegression::train(&rows, &labels, &LogisticParams::default());
+
+        let predicted: Vec<usize> = model.predict_batch(&rows).iter().map(|p| p.class).collect();
+        assert_eq!(predicted, labels);
+        assert!(model.predict(&[110.0f32]).probability > 0.99f32);
+    }
+
+    #[test]
+    fn probabilities_sum_to_one() {
+        let model = LogisticRegression::new(vec![1.0f32, -2.0f32], 0.5f32);
+        let mut p = vec![];
+        model.probabilities(&[0.3f32, 0.1f32], &mut p);
+
+        assert_eq!(p.len(), 2);
+        assert!((p[0] + p[1] - 1.0f32).abs() < 1e-6f32);
+        assert!((model.score(&[0.3f32, 0.1f32]) - p[1]).abs() < 1e-6f32);
+    }
+
+    #[test]
+    fn update_rejects_multi_class_labels() {
+        let mut model = LogisticRegression::new(vec![1.0f32], 0.0f32);
+
+        for label in [DIGIT, LETTER, BORDER].iter() {
+            assert!(!model.update(&[1.0f32], *label, 0.5f32));
+        }
+        assert_eq!(model, LogisticRegression::new(vec![1.0f32], 0.0f32));
+
+        assert!(model.update(&[1.0f32], CHARACTER, 0.5f32));
+        assert!(model.update(&[1.0f32], BACKGROUND, 0.5f32));
+    }
+}
@@ -0,0 +1,172 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use super::classifier::{Classifier, BACKGROUND};
+use super::dataset::{Sample, RegionKey};
+use super::rng::XorShift;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MiningParams {
+    /// Number of times the detector is re-run with the retrained model.
+    pub rounds: usize,
+    /// Background regions scored at least this much are hard negatives.
+    pub min_score: f32,
+    /// Upper bound of hard negatives taken from a single image per round,
+    /// so that a single fence doesn't flood the dataset.
+    pub max_per_image: usize,
+    /// Upper bound of background samples taken from a single image before
+    /// mining. The rest is left for the mining rounds to pick from.
+    pub max_background: usize,
+    /// Seed of the background subsampling.
+    pub seed: u64
+}
+
+impl Default for MiningParams {
+    fn default() -> MiningParams {
+        MiningParams {
+            rounds: 0,
+            min_score: 0.5f32,
+            max_per_image: 50,
+            max_background: 100,
+            seed: 1
+        }
+    }
+}
+
+/// Keeps all character samples of a single image and a random subset of
+/// at most `params.max_background` background ones, in the original order.
+pub fn subsample_background(samples: Vec<Sample>, params: &MiningParams) -> Vec<Sample> {
+    let mut background: Vec<usize> = (0..samples.len()).filter(|i| samples[*i].label == BACKGROUND).collect();
+    XorShift::new(params.seed).shuffle(&mut background);
+    let dropped: HashSet<usize> = background.into_iter().skip(params.max_background).collect();
+
+    samples.into_iter()
+        .enumerate()
+        .filter(|&(i, _)| !dropped.contains(&i))
+        .map(|(_, s)| s)
+        .collect()
+}
+
+/// Picks false positives of `classifier` among labeled `candidates` of a
+/// single image: background regions with the highest scores that are not
+/// among `known` regions of the dataset yet, see `Dataset::region_keys`.
+pub fn hard_negatives<C: Classifier>(
+    classifier: &C,
+    candidates: Vec<Sample>,
+    known: &HashSet<RegionKey>,
+    params: &MiningParams
+) -> Vec<Sample> {
+    let mut scored: Vec<(f32, Sample)> = candidates.into_iter()
+        .filter(|s| s.label == BACKGROUND)
+        .map(|s| (classifier.score(&s.features), s))
+        .filter(|&(score, _)| score >= params.min_score)
+        .collect();
+
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+    scored.into_iter()
+        .map(|(_, s)| s)
+        .filter(|s| !known.contains(&s.region_key()))
+        .take(params.max_per_image)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Image;
+    use structures::{Point, Rect};
+    use extract::RegionDetector;
+    use extract::cser::{Region, RegionParams, EmptyTrace, CserDetector};
+    use extract::cser::feature::{FeatureSet, Schema, parse_layout};
+    use ml::classifier::{BACKGROUND, CHARACTER};
+    use ml::dataset::{Dataset, Sample};
+    use ml::ground_truth::Labeling;
+    use ml::logistic::LogisticRegression;
+
+    fn sample(x: f32, label: usize) -> Sample {
+        Sample {
+            features: vec![x],
+            label: label,
+            image: "a.png".to_string(),
+            bounds: Rect(Point { x: x as i32, y: 0 }, Point { x: x as i32 + 5, y: 5 }),
+            thres: 10
+        }
+    }
+
+    #[test]
+    fn picks_unseen_high_scoring_background() {
+        // score grows with the feature value
+        let classifier = LogisticRegression::new(vec![1.0f32], 0.0f32);
+
+        let mut dataset = Dataset::new(Schema { columns: vec![] });
+        dataset.samples.push(sample(4.0f32, BACKGROUND));
+
+        let candidates = vec![
+            sample(-3.0f32, BACKGROUND), sample(2.0f32, BACKGROUND), sample(3.0f32, CHARACTER),
+            sample(4.0f32, BACKGROUND), sample(5.0f32, BACKGROUND), sample(1.0f32, BACKGROUND)
+        ];
+        let params = MiningParams { max_per_image: 2, ..MiningParams::default() };
+
+        let res: Vec<f32> = hard_negatives(&classifier, candidates, &dataset.region_keys(), &params).iter()
+            .map(|s| s.features[0])
+            .collect();
+
+        assert_eq!(res, vec![5.0f32, 2.0f32]);
+    }
+
+    #[test]
+    fn subsampling_keeps_characters() {
+        let samples: Vec<Sample> = (0..10)
+            .map(|i| sample(i as f32, if i % 3 == 0 { CHARACTER } else { BACKGROUND }))
+            .collect();
+        let params = MiningParams { max_background: 2, ..MiningParams::default() };
+
+        let res = subsample_background(samples, &params);
+
+        assert_eq!(res.len(), 6);
+        assert_eq!(res.iter().filter(|s| s.label == CHARACTER).count(), 4);
+    }
+
+    #[test]
+    fn mining_round_adds_regions_left_out_before() {
+        // six dark blobs, the first two of them are annotated characters
+        let (width, height) = (60, 20);
+        let mut data = vec![255u8; width * height];
+        for i in 0..6 {
+            for x in (3 + 9 * i)..(8 + 9 * i) {
+                for y in 4..12 {
+                    data[y * width + x] = 0;
+                }
+            }
+        }
+        let img = Image::from_data(data, width, height);
+        let truth = vec![
+            (Rect(Point { x: 3, y: 4 }, Point { x: 7, y: 11 }), CHARACTER),
+            (Rect(Point { x: 12, y: 4 }, Point { x: 16, y: 11 }), CHARACTER)
+        ];
+
+        let layout = parse_layout("aspect_ratio").unwrap();
+        let regions = CserDetector::<Region<FeatureSet>, EmptyTrace>::detect(&img, &RegionParams::new(layout.clone()), &mut EmptyTrace);
+        let candidates = Labeling::default().samples("synthetic.png", &regions, &truth);
+        assert_eq!(candidates.iter().filter(|s| s.label == BACKGROUND).count(), 4);
+
+        let params = MiningParams { rounds: 2, max_background: 2, ..MiningParams::default() };
+        let mut dataset = Dataset::new(FeatureSet::schema(&layout));
+        for s in subsample_background(candidates.clone(), &params) {
+            dataset.push(s);
+        }
+        assert_eq!((dataset.num_characters(), dataset.count(BACKGROUND)), (2, 2));
+
+        // takes every region for a character
+        let classifier = LogisticRegression::new(vec![0.0f32], 4.0f32);
+
+        let mined = hard_negatives(&classifier, candidates.clone(), &dataset.region_keys(), &params);
+        assert_eq!(mined.len(), 2);
+        for s in mined {
+            dataset.push(s);
+        }
+
+        assert_eq!(hard_negatives(&classifier, candidates, &dataset.region_keys(), &params).len(), 0);
+    }
+}
@@ -0,0 +1,332 @@
+use std::cmp;
+
+use super::classifier::Classifier;
+use super::rng::XorShift;
+use super::online::OnlineUpdate;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Optimizer {
+    Sgd { momentum: f32 },
+    Adam { beta1: f32, beta2: f32, epsilon: f32 }
+}
+
+impl Optimizer {
+    pub fn adam() -> Optimizer {
+        Optimizer::Adam { beta1: 0.9f32, beta2: 0.999f32, epsilon: 1e-8f32 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlpParams {
+    /// Sizes of hidden layers.
+    pub hidden: Vec<usize>,
+    pub epochs: usize,
+    pub batch_size: usize,
+    pub learning_rate: f32,
+    pub l2: f32,
+    pub optimizer: Optimizer,
+    pub seed: u64
+}
+
+impl Default for MlpParams {
+    fn default() -> MlpParams {
+        MlpParams {
+            hidden: vec![32],
+            epochs: 100,
+            batch_size: 32,
+            learning_rate: 0.01f32,
+            l2: 1e-4f32,
+            optimizer: Optimizer::adam(),
+            seed: 1
+        }
+    }
+}
+
+/// Fully connected layer, `weights[o * inputs + i]` connects input `i`
+/// with output `o`.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+struct Layer {
+    inputs: usize,
+    outputs: usize,
+    weights: Vec<f32>,
+    biases: Vec<f32>
+}
+
+impl Layer {
+    /// He initialization, suited for ReLU activations.
+    fn new(inputs: usize, outputs: usize, rng: &mut XorShift) -> Layer {
+        let std = (2.0f32 / (cmp::max(inputs, 1) as f32)).sqrt();
+        Layer {
+            inputs: inputs,
+            outputs: outputs,
+            weights: (0..(inputs * outputs)).map(|_| rng.normal() * std).collect(),
+            biases: vec![0.0f32; outputs]
+        }
+    }
+
+    fn forward(&self, x: &[f32]) -> Vec<f32> {
+        (0..self.outputs)
+            .map(|o| {
+                let w = &self.weights[(o * self.inputs)..((o + 1) * self.inputs)];
+                w.iter().zip(x.iter()).fold(self.biases[o], |a, (w, x)| a + w * x)
+            })
+            .collect()
+    }
+}
+
+fn softmax(z: &mut [f32]) {
+    let max = z.iter().cloned().fold(-1.0f32 / 0.0f32, f32::max);
+    let mut sum = 0.0f32;
+    for v in z.iter_mut() {
+        *v = (*v - max).exp();
+        sum += *v;
+    }
+    for v in z.iter_mut() {
+        *v /= sum;
+    }
+}
+
+/// First and second moments of gradients kept by the optimizer for a
+/// parameter vector.
+struct Moments {
+    m: Vec<f32>,
+    v: Vec<f32>
+}
+
+impl Moments {
+    fn new(n: usize) -> Moments {
+        Moments { m: vec![0.0f32; n], v: vec![0.0f32; n] }
+    }
+
+    /// Applies gradient `g` to `params` at step `t` (starting with 1).
+    fn step(&mut self, params: &mut [f32], g: &[f32], opt: Optimizer, lr: f32, t: i32) {
+        match opt {
+            Optimizer::Sgd { momentum } => {
+                for i in 0..params.len() {
+                    self.m[i] = momentum * self.m[i] + g[i];
+                    params[i] -= lr * self.m[i];
+                }
+            },
+            Optimizer::Adam { beta1, beta2, epsilon } => {
+                let (c1, c2) = (1.0f32 - beta1.powi(t), 1.0f32 - beta2.powi(t));
+                for i in 0..params.len() {
+                    self.m[i] = beta1 * self.m[i] + (1.0f32 - beta1) * g[i];
+                    self.v[i] = beta2 * self.v[i] + (1.0f32 - beta2) * g[i] * g[i];
+                    params[i] -= lr * (self.m[i] / c1) / ((self.v[i] / c2).sqrt() + epsilon);
+                }
+            }
+        }
+    }
+}
+
+/// Multi-layer perceptron with ReLU hidden layers and softmax output,
+/// trained by backpropagation of cross-entropy loss.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Mlp {
+    layers: Vec<Layer>
+}
+
+impl Mlp {
+    /// Randomly initialized network with layer sizes `sizes`, the first one
+    /// is the number of inputs and the last one is the number of classes.
+    pub fn new(sizes: &[usize], seed: u64) -> Mlp {
+        let mut rng = XorShift::new(seed);
+        Mlp {
+            layers: sizes.windows(2).map(|s| Layer::new(s[0], s[1], &mut rng)).collect()
+        }
+    }
+
+    /// Outputs of every layer, starting with the input itself.
+    fn activations(&self, x: &[f32]) -> Vec<Vec<f32>> {
+        let mut res = vec![x.to_vec()];
+        for (l, layer) in self.layers.iter().enumerate() {
+            let mut z = layer.forward(&res[l]);
+            if l + 1 == self.layers.len() {
+                softmax(&mut z);
+            } else {
+                for v in z.iter_mut() {
+                    *v = v.max(0.0f32);
+                }
+            }
+            res.push(z);
+        }
+        res
+    }
+
+    pub fn train(rows: &[Vec<f32>], labels: &[usize], params: &MlpParams) -> Mlp {
+        debug_assert!(rows.len() == labels.len());
+
+        let num_inputs = rows.first().map(|r| r.len()).unwrap_or(0);
+        let num_classes = cmp::max(labels.iter().cloned().max().map(|l| l + 1).unwrap_or(0), 2);
+
+        let mut sizes = vec![num_inputs];
+        sizes.extend(params.hidden.iter().cloned());
+        sizes.push(num_classes);
+
+        let mut net = Mlp::new(&sizes, params.seed);
+        let mut rng = XorShift::new(params.seed.wrapping_add(1));
+
+        let mut moments: Vec<(Moments, Moments)> = net.layers.iter()
+            .map(|l| (Moments::new(l.weights.len()), Moments::new(l.biases.len())))
+            .collect();
+
+        let mut order: Vec<usize> = (0..rows.len()).collect();
+        let batch_size = cmp::max(params.batch_size, 1);
+        let mut t = 0;
+
+        for _ in 0..params.epochs {
+            rng.shuffle(&mut order);
+
+            for batch in order.chunks(batch_size) {
+                let mut grads: Vec<(Vec<f32>, Vec<f32>)> = net.layers.iter()
+                    .map(|l| (vec![0.0f32; l.weights.len()], vec![0.0f32; l.biases.len()]))
+                    .collect();
+
+                for &i in batch {
+                    net.backprop(&rows[i], labels[i], &mut grads);
+                }
+
+                t += 1;
+                let scale = 1.0f32 / (batch.len() as f32);
+                for ((layer, grad), moment) in net.layers.iter_mut().zip(grads.iter_mut()).zip(moments.iter_mut()) {
+                    for (g, w) in grad.0.iter_mut().zip(layer.weights.iter()) {
+                        *g = *g * scale + params.l2 * w;
+                    }
+                    for g in grad.1.iter_mut() {
+                        *g *= scale;
+                    }
+
+                    moment.0.step(&mut layer.weights, &grad.0, params.optimizer, params.learning_rate, t);
+                    moment.1.step(&mut layer.biases, &grad.1, params.optimizer, params.learning_rate, t);
+                }
+            }
+        }
+
+        net
+    }
+
+    /// Adds gradients of cross-entropy loss of a single sample to `grads`.
+    fn backprop(&self, x: &[f32], label: usize, grads: &mut [(Vec<f32>, Vec<f32>)]) {
+        let a = self.activations(x);
+
+        // gradient of the loss with respect to pre-softmax outputs
+        let mut delta = a[self.layers.len()].clone();
+        delta[label] -= 1.0f32;
+
+        for l in (0..self.layers.len()).rev() {
+            let layer = &self.layers[l];
+            let input = &a[l];
+
+            for o in 0..layer.outputs {
+                grads[l].1[o] += delta[o];
+                for i in 0..layer.inputs {
+                    grads[l].0[o * layer.inputs + i] += delta[o] * input[i];
+                }
+            }
+
+            if l > 0 {
+                // through ReLU of the previous layer
+                delta = (0..layer.inputs)
+                    .map(|i| {
+                        if input[i] <= 0.0f32 {
+                            return 0.0f32;
+                        }
+                        (0..layer.outputs).fold(0.0f32, |s, o| s + layer.weights[o * layer.inputs + i] * delta[o])
+                    })
+                    .collect();
+            }
+        }
+    }
+}
+
+impl Classifier for Mlp {
+    fn num_classes(&self) -> usize {
+        self.layers.last().map(|l| l.outputs).unwrap_or(0)
+    }
+
+    fn probabilities(&self, x: &[f32], out: &mut Vec<f32>) {
+        let mut a = self.activations(x);
+        *out = a.pop().unwrap();
+    }
+}
+
+impl OnlineUpdate for Mlp {
+    /// Single stochastic gradient descent step without momentum. Classes
+    /// unknown to the network can't be learned this way.
+    fn update(&mut self, x: &[f32], label: usize, learning_rate: f32) -> bool {
+        if label >= self.num_classes() {
+            return false;
+        }
+
+        let mut grads: Vec<(Vec<f32>, Vec<f32>)> = self.layers.iter()
+            .map(|l| (vec![0.0f32; l.weights.len()], vec![0.0f32; l.biases.len()]))
+            .collect();
+        self.backprop(x, label, &mut grads);
+
+        for (layer, grad) in self.layers.iter_mut().zip(grads.iter()) {
+            for (w, g) in layer.weights.iter_mut().zip(grad.0.iter()) {
+                *w -= learning_rate * g;
+            }
+            for (b, g) in layer.biases.iter_mut().zip(grad.1.iter()) {
+                *b -= learning_rate * g;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ml::classifier::Classifier;
+
+    #[test]
+    fn gradients_match_finite_differences() {
+        let net = Mlp::new(&[3, 4, 3], 3);
+        let (x, label) = (vec![0.5f32, -1.0f32, 2.0f32], 2);
+
+        let mut grads: Vec<(Vec<f32>, Vec<f32>)> = net.layers.iter()
+            .map(|l| (vec![0.0f32; l.weights.len()], vec![0.0f32; l.biases.len()]))
+            .collect();
+        net.backprop(&x, label, &mut grads);
+
+        let loss = |n: &Mlp| -> f32 {
+            let mut p = vec![];
+            n.probabilities(&x, &mut p);
+            -p[label].ln()
+        };
+
+        let h = 1e-3f32;
+        for l in 0..2 {
+            for w in 0..net.layers[l].weights.len() {
+                let (mut plus, mut minus) = (net.clone(), net.clone());
+                plus.layers[l].weights[w] += h;
+                minus.layers[l].weights[w] -= h;
+                let numeric = (loss(&plus) - loss(&minus)) / (2.0f32 * h);
+                assert!((numeric - grads[l].0[w]).abs() < 1e-2, "layer {} weight {}", l, w);
+            }
+        }
+    }
+
+    #[test]
+    fn learns_xor() {
+        let rows = vec![vec![0.0f32, 0.0f32], vec![0.0f32, 1.0f32], vec![1.0f32, 0.0f32], vec![1.0f32, 1.0f32]];
+        let labels = vec![0, 1, 1, 0];
+
+        for optimizer in [Optimizer::adam(), Optimizer::Sgd { momentum: 0.9f32 }].iter() {
+            let params = MlpParams {
+                hidden: vec![8],
+                epochs: 2000,
+                batch_size: 4,
+                learning_rate: 0.05f32,
+                l2: 0.0f32,
+                optimizer: *optimizer,
+                seed: 7
+            };
+            let net = Mlp::train(&rows, &labels, &params);
+
+            let predicted: Vec<usize> = net.predict_batch(&rows).iter().map(|p| p.class).collect();
+            assert_eq!(predicted, labels, "{:?}", optimizer);
+        }
+    }
+}
@@ -1 +1,31 @@
-mod classifier;
+pub mod classifier;
+pub mod normalize;
+pub mod logistic;
+pub mod adaboost;
+pub mod forest;
+pub mod mlp;
+pub mod rng;
+pub mod trainer;
+pub mod eval;
+pub mod mining;
+pub mod calibration;
+pub mod online;
+pub mod explain;
+pub mod dataset;
+pub mod ground_truth;
+pub mod model;
+pub mod ocr;
+
+pub use self::classifier::{Classifier, Prediction, Normalized};
+pub use self::logistic::{LogisticRegression, LogisticParams};
+pub use self::adaboost::{AdaBoost, AdaBoostParams, Stump};
+pub use self::forest::{RandomForest, ForestParams};
+pub use self::mlp::{Mlp, MlpParams, Optimizer};
+pub use self::trainer::{Trainer, ModelTrainer, Algorithm};
+pub use self::eval::{Evaluation, OutOfFold, evaluate, cross_validate, out_of_fold};
+pub use self::calibration::{Calibration, CalibrationKind};
+pub use self::mining::{MiningParams, hard_negatives, subsample_background};
+pub use self::online::{OnlineUpdate, FeedbackQueue};
+pub use self::explain::{Explanation, FeatureContribution, Units, explain};
+pub use self::dataset::{Dataset, Sample, RegionKey, DatasetError};
+pub use self::model::{Model, ClassifierModel, ModelError};
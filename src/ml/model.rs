@@ -0,0 +1,243 @@
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+
+use bincode::SizeLimit;
+use bincode::rustc_serialize::{encode_into, decode_from, EncodingError, DecodingError};
+use rustc_serialize::{Encodable, Decodable};
+
+use extract::cser::feature::{Schema, SchemaMismatch};
+use super::classifier::{Classifier, Normalized};
+use super::logistic::LogisticRegression;
+use super::adaboost::AdaBoost;
+use super::forest::RandomForest;
+use super::mlp::Mlp;
+use super::calibration::Calibration;
+
+static MAGIC: &'static str = "nprs-model";
+
+/// Version of the on-disk model format. Bump when `Model` or any of the
+/// classifiers change their serialized representation.
+pub static MODEL_VERSION: u32 = 4;
+
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+pub enum ClassifierModel {
+    Logistic(LogisticRegression),
+    AdaBoost(AdaBoost),
+    RandomForest(RandomForest),
+    Mlp(Mlp)
+}
+
+impl Classifier for ClassifierModel {
+    fn num_classes(&self) -> usize {
+        match *self {
+            ClassifierModel::Logistic(ref c) => c.num_classes(),
+            ClassifierModel::AdaBoost(ref c) => c.num_classes(),
+            ClassifierModel::RandomForest(ref c) => c.num_classes(),
+            ClassifierModel::Mlp(ref c) => c.num_classes()
+        }
+    }
+
+    fn probabilities(&self, x: &[f32], out: &mut Vec<f32>) {
+        match *self {
+            ClassifierModel::Logistic(ref c) => c.probabilities(x, out),
+            ClassifierModel::AdaBoost(ref c) => c.probabilities(x, out),
+            ClassifierModel::RandomForest(ref c) => c.probabilities(x, out),
+            ClassifierModel::Mlp(ref c) => c.probabilities(x, out)
+        }
+    }
+}
+
+/// Trained region classifier with everything needed to apply it: names of
+/// the runtime features it was trained on, schema of the feature vectors,
+/// fitted normalization and calibration of its scores.
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+pub struct Model {
+    pub features: String,
+    /// Second-stage features following the runtime features in feature
+    /// vectors, empty for models weighting regions during detection.
+    pub second_stage: String,
+    pub schema: Schema,
+    /// Classifier with the normalization of its inputs.
+    pub classifier: Normalized<ClassifierModel>,
+    pub calibration: Calibration
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct Header {
+    magic: String,
+    version: u32
+}
+
+#[derive(Debug)]
+pub enum ModelError {
+    IoError(io::Error),
+    EncodeError(EncodingError),
+    DecodeError(DecodingError),
+    NotAModel,
+    UnsupportedVersion(u32),
+    SchemaMismatch(SchemaMismatch)
+}
+
+pub type ModelResult<T> = Result<T, ModelError>;
+
+/// Writes `value` preceded by a header with file type `magic` and format
+/// `version`.
+pub fn write_versioned<T: Encodable, W: Write>(magic: &str, version: u32, value: &T, w: &mut W) -> ModelResult<()> {
+    let header = Header { magic: magic.to_string(), version: version };
+    try!(encode_into(&header, w, SizeLimit::Infinite).map_err(|e| ModelError::EncodeError(e)));
+    encode_into(value, w, SizeLimit::Infinite).map_err(|e| ModelError::EncodeError(e))
+}
+
+/// Reads value written by `write_versioned` with the same `magic` and `version`.
+pub fn read_versioned<T: Decodable, R: Read>(magic: &str, version: u32, r: &mut R) -> ModelResult<T> {
+    let header: Header = try!(decode_from(r, SizeLimit::Infinite).map_err(|_| ModelError::NotAModel));
+    if header.magic != magic {
+        return Err(ModelError::NotAModel);
+    }
+    if header.version != version {
+        return Err(ModelError::UnsupportedVersion(header.version));
+    }
+    decode_from(r, SizeLimit::Infinite).map_err(|e| ModelError::DecodeError(e))
+}
+
+impl Model {
+    pub fn write<W: Write>(&self, w: &mut W) -> ModelResult<()> {
+        write_versioned(MAGIC, MODEL_VERSION, self, w)
+    }
+
+    pub fn read<R: Read>(r: &mut R) -> ModelResult<Model> {
+        read_versioned(MAGIC, MODEL_VERSION, r)
+    }
+
+    pub fn save(&self, path: &str) -> ModelResult<()> {
+        let mut f = try!(File::create(path).map_err(|e| ModelError::IoError(e)));
+        self.write(&mut f)
+    }
+
+    pub fn load(path: &str) -> ModelResult<Model> {
+        let mut f = try!(File::open(path).map_err(|e| ModelError::IoError(e)));
+        Model::read(&mut f)
+    }
+
+    /// Checks that feature vectors described by `actual` can be fed to the model.
+    pub fn check_schema(&self, actual: &Schema) -> ModelResult<()> {
+        self.schema.check(actual).map_err(|e| ModelError::SchemaMismatch(e))
+    }
+}
+
+impl Classifier for Model {
+    fn num_classes(&self) -> usize {
+        self.classifier.num_classes()
+    }
+
+    fn probabilities(&self, x: &[f32], out: &mut Vec<f32>) {
+        self.classifier.probabilities(x, out);
+        self.calibration.apply_probabilities(out);
+    }
+
+    fn probabilities_with(&self, x: &[f32], out: &mut Vec<f32>, scratch: &mut Vec<f32>) {
+        self.classifier.probabilities_with(x, out, scratch);
+        self.calibration.apply_probabilities(out);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use bincode::SizeLimit;
+    use bincode::rustc_serialize::encode_into;
+
+    use extract::cser::feature::{Schema, AspectRatio, NumHoles};
+    use ml::normalize::{Normalizer, StepSpec};
+    use ml::logistic::LogisticRegression;
+    use ml::adaboost::{AdaBoost, Stump};
+    use ml::classifier::{Classifier, Normalized};
+    use ml::calibration::Calibration;
+    use ml::mlp::Mlp;
+    use super::*;
+    use super::{Header, MAGIC};
+
+    fn model() -> Model {
+        let rows = vec![vec![1.0f32, 0.0f32], vec![3.0f32, 1.0f32]];
+        Model {
+            features: "aspect_ratio,num_holes".to_string(),
+            second_stage: String::new(),
+            schema: Schema::of::<(AspectRatio, NumHoles)>(),
+            classifier: Normalized {
+                normalizer: Normalizer::fit(&[StepSpec::Standardize], &rows),
+                classifier: ClassifierModel::Logistic(LogisticRegression::new(vec![1.0f32, 2.0f32], -0.5f32))
+            },
+            calibration: Calibration::Platt { a: 1.5f32, b: 0.2f32 }
+        }
+    }
+
+    #[test]
+    fn write_read_roundtrip() {
+        let m = model();
+        let mut buf = vec![];
+        m.write(&mut buf).unwrap();
+
+        let loaded = Model::read(&mut Cursor::new(buf)).unwrap();
+
+        assert_eq!(loaded.schema, m.schema);
+        assert_eq!(loaded.classifier.normalizer, m.classifier.normalizer);
+        assert_eq!(loaded.score(&[2.0f32, 1.0f32]), m.score(&[2.0f32, 1.0f32]));
+    }
+
+    #[test]
+    fn adaboost_roundtrip() {
+        let mut m = model();
+        m.classifier.classifier = ClassifierModel::AdaBoost(AdaBoost::from_stumps(2, vec![
+            Stump { feature: 0, threshold: 0.0f32, left: -1.0f32, right: 1.0f32 },
+            Stump { feature: 1, threshold: 0.5f32, left: 0.5f32, right: -0.5f32 },
+        ]));
+
+        let mut buf = vec![];
+        m.write(&mut buf).unwrap();
+        let loaded = Model::read(&mut Cursor::new(buf)).unwrap();
+
+        for x in [[2.0f32, 1.0f32], [1.0f32, 0.0f32], [3.0f32, 0.0f32]].iter() {
+            assert_eq!(loaded.score(x), m.score(x));
+        }
+    }
+
+    #[test]
+    fn mlp_roundtrip() {
+        let mut m = model();
+        m.classifier.classifier = ClassifierModel::Mlp(Mlp::new(&[2, 4, 2], 5));
+
+        let mut buf = vec![];
+        m.write(&mut buf).unwrap();
+        let loaded = Model::read(&mut Cursor::new(buf)).unwrap();
+
+        for x in [[2.0f32, 1.0f32], [1.0f32, 0.0f32], [3.0f32, 0.0f32]].iter() {
+            assert_eq!(loaded.score(x), m.score(x));
+        }
+    }
+
+    #[test]
+    fn read_other_file() {
+        let mut buf = vec![];
+        let header = Header { magic: "something else".to_string(), version: MODEL_VERSION };
+        encode_into(&header, &mut buf, SizeLimit::Infinite).unwrap();
+
+        match Model::read(&mut Cursor::new(buf)) {
+            Err(ModelError::NotAModel) => {},
+            r => panic!("expected NotAModel, got {:?}", r)
+        }
+    }
+
+    #[test]
+    fn read_unsupported_version() {
+        let mut buf = vec![];
+        let header = Header { magic: MAGIC.to_string(), version: MODEL_VERSION + 1 };
+        encode_into(&header, &mut buf, SizeLimit::Infinite).unwrap();
+
+        match Model::read(&mut Cursor::new(buf)) {
+            Err(ModelError::UnsupportedVersion(v)) => assert_eq!(v, MODEL_VERSION + 1),
+            r => panic!("expected UnsupportedVersion, got {:?}", r)
+        }
+    }
+}
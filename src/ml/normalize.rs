@@ -0,0 +1,215 @@
+use std::cmp::{self, Ordering};
+
+/// Transform step to be fitted on training data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepSpec {
+    /// `sign(x) * ln(1 + |x|)`, compresses heavy tails.
+    Log,
+    /// Clip to fixed range.
+    Clip(f32, f32),
+    /// Clip to the range between `q` and `1 - q` quantiles of the data.
+    ClipQuantile(f32),
+    /// Zero mean, unit variance.
+    Standardize,
+    /// Scale to `0..1` range.
+    MinMax
+}
+
+/// Fitted transform step.
+#[derive(Debug, Clone, Copy, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Step {
+    Log,
+    Clip { min: f32, max: f32 },
+    Standardize { mean: f32, std: f32 },
+    MinMax { min: f32, max: f32 }
+}
+
+impl Step {
+    pub fn fit(spec: StepSpec, values: &[f32]) -> Step {
+        match spec {
+            StepSpec::Log => Step::Log,
+            StepSpec::Clip(min, max) => Step::Clip { min: min, max: max },
+            StepSpec::ClipQuantile(q) => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                Step::Clip { min: quantile(&sorted, q), max: quantile(&sorted, 1.0f32 - q) }
+            },
+            StepSpec::Standardize => {
+                let n = cmp::max(values.len(), 1) as f32;
+                let mean = values.iter().fold(0.0f32, |a, v| a + v) / n;
+                let var = values.iter().fold(0.0f32, |a, v| a + (v - mean) * (v - mean)) / n;
+                Step::Standardize { mean: mean, std: var.sqrt() }
+            },
+            StepSpec::MinMax => {
+                let min = values.iter().cloned().fold(0.0f32 / 0.0f32, f32::min);
+                let max = values.iter().cloned().fold(0.0f32 / 0.0f32, f32::max);
+                Step::MinMax { min: min, max: max }
+            }
+        }
+    }
+
+    pub fn apply(&self, v: f32) -> f32 {
+        match *self {
+            Step::Log => v.signum() * v.abs().ln_1p(),
+            Step::Clip { min, max } => v.max(min).min(max),
+            Step::Standardize { mean, std } => if std > 0.0f32 { (v - mean) / std } else { 0.0f32 },
+            Step::MinMax { min, max } => if max > min { (v - min) / (max - min) } else { 0.0f32 }
+        }
+    }
+}
+
+fn quantile(sorted: &[f32], q: f32) -> f32 {
+    if sorted.len() == 0 {
+        return 0.0f32;
+    }
+    let i = (q * ((sorted.len() - 1) as f32)).round() as usize;
+    sorted[cmp::min(i, sorted.len() - 1)]
+}
+
+/// Parses comma separated list of steps, e.g. `log,clip_quantile:0.01,standardize`.
+pub fn parse_steps(s: &str) -> Result<Vec<StepSpec>, String> {
+    let mut steps = vec![];
+    for step in s.split(',').map(|x| x.trim()).filter(|x| x.len() > 0) {
+        let parts: Vec<&str> = step.split(':').collect();
+        let args: Result<Vec<f32>, _> = parts[1..].iter().map(|a| a.parse::<f32>()).collect();
+        let args = try!(args.map_err(|e| format!("invalid argument of `{}`: {}", step, e)));
+
+        let spec = match (parts[0], &args[..]) {
+            ("log", []) => StepSpec::Log,
+            ("clip", [min, max]) => StepSpec::Clip(min, max),
+            ("clip_quantile", [q]) => StepSpec::ClipQuantile(q),
+            ("standardize", []) => StepSpec::Standardize,
+            ("min_max", []) => StepSpec::MinMax,
+            _ => return Err(format!("unknown normalization step `{}`", step))
+        };
+        steps.push(spec);
+    }
+    Ok(steps)
+}
+
+/// Per-column chain of fitted transform steps. Fitted once on training data
+/// and stored with the model, so exactly the same transform is applied
+/// during training and during scoring of regions in the detector.
+#[derive(Debug, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Normalizer {
+    columns: Vec<Vec<Step>>,
+    means: Vec<f32>
+}
+
+impl Normalizer {
+    /// Normalizer that leaves `n` columns unchanged. It isn't fitted, so
+    /// means of all columns are zero.
+    pub fn identity(n: usize) -> Normalizer {
+        Normalizer { columns: vec![vec![]; n], means: vec![0.0f32; n] }
+    }
+
+    /// Fits the same chain of steps for every column of `rows`.
+    pub fn fit(steps: &[StepSpec], rows: &[Vec<f32>]) -> Normalizer {
+        let n = rows.first().map(|r| r.len()).unwrap_or(0);
+        let specs = vec![steps.to_vec(); n];
+        Normalizer::fit_columns(&specs, rows)
+    }
+
+    /// Fits separate chain of steps for every column. Each step is fitted on
+    /// the output of the previous one.
+    pub fn fit_columns(specs: &[Vec<StepSpec>], rows: &[Vec<f32>]) -> Normalizer {
+        let mut columns = vec![];
+        let mut means = vec![];
+
+        for (c, spec) in specs.iter().enumerate() {
+            let mut values: Vec<f32> = rows.iter().map(|r| r[c]).collect();
+            let mut steps = vec![];
+
+            for s in spec {
+                let step = Step::fit(*s, &values);
+                for v in values.iter_mut() {
+                    *v = step.apply(*v);
+                }
+                steps.push(step);
+            }
+
+            let n = cmp::max(values.len(), 1) as f32;
+            means.push(values.iter().fold(0.0f32, |a, v| a + v) / n);
+            columns.push(steps);
+        }
+
+        Normalizer { columns: columns, means: means }
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn steps<'a>(&'a self, column: usize) -> &'a [Step] {
+        &self.columns[column][..]
+    }
+
+    /// Mean of every column of the training rows after normalization.
+    pub fn means<'a>(&'a self) -> &'a [f32] {
+        &self.means[..]
+    }
+
+    pub fn apply(&self, x: &mut [f32]) {
+        debug_assert!(x.len() == self.columns.len());
+        for (v, steps) in x.iter_mut().zip(self.columns.iter()) {
+            for s in steps {
+                *v = s.apply(*v);
+            }
+        }
+    }
+
+    pub fn apply_all(&self, rows: &mut [Vec<f32>]) {
+        for r in rows.iter_mut() {
+            self.apply(&mut r[..]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn standardize() {
+        let rows = vec![vec![1.0f32, 10.0f32], vec![3.0f32, 10.0f32]];
+        let n = Normalizer::fit(&[StepSpec::Standardize], &rows);
+
+        let mut x = vec![3.0f32, 12.0f32];
+        n.apply(&mut x);
+
+        // constant column has zero variance and is mapped to zero
+        assert_eq!(x, vec![1.0f32, 0.0f32]);
+    }
+
+    #[test]
+    fn steps_are_fitted_on_transformed_values() {
+        let rows = vec![vec![0.0f32], vec![1.0f32], vec![100.0f32]];
+        let n = Normalizer::fit(&[StepSpec::Clip(0.0f32, 10.0f32), StepSpec::MinMax], &rows);
+
+        assert_eq!(n.steps(0)[1], Step::MinMax { min: 0.0f32, max: 10.0f32 });
+        // normalized values are 0, 0.1 and 1
+        assert!((n.means()[0] - 1.1f32 / 3.0f32).abs() < 1e-6);
+
+        let mut x = vec![5.0f32];
+        n.apply(&mut x);
+        assert_eq!(x, vec![0.5f32]);
+    }
+
+    #[test]
+    fn clip_quantile() {
+        let rows: Vec<Vec<f32>> = (0..101).map(|i| vec![i as f32]).collect();
+        let n = Normalizer::fit(&[StepSpec::ClipQuantile(0.1f32)], &rows);
+
+        assert_eq!(n.steps(0)[0], Step::Clip { min: 10.0f32, max: 90.0f32 });
+    }
+
+    #[test]
+    fn parse_steps_test() {
+        assert_eq!(
+            parse_steps("log, clip_quantile:0.05,standardize"),
+            Ok(vec![StepSpec::Log, StepSpec::ClipQuantile(0.05f32), StepSpec::Standardize])
+        );
+        assert!(parse_steps("clip:1").is_err());
+        assert!(parse_steps("foo").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use std::f32::consts::PI;
+
+use super::glyph::{Glyph, GLYPH_WIDTH, GLYPH_HEIGHT};
+
+/// Fixed length description of a glyph bitmap.
+#[derive(Debug, Copy, Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Descriptor {
+    /// Mean ink coverage of every cell of `cols x rows` grid.
+    Zoning { cols: usize, rows: usize },
+    /// Mean ink of row bands and of column bands.
+    Projections { bins: usize },
+    /// Histograms of gradient orientations of `cell x cell` pixel cells,
+    /// weighted by gradient magnitude and L2 normalized per cell.
+    Hog { cell: usize, bins: usize }
+}
+
+pub static DEFAULT_DESCRIPTORS: &'static str = "zoning,projections,hog";
+
+impl Descriptor {
+    pub fn from_name(name: &str) -> Option<Descriptor> {
+        match name {
+            "zoning" => Some(Descriptor::Zoning { cols: 4, rows: 6 }),
+            "projections" => Some(Descriptor::Projections { bins: 8 }),
+            "hog" => Some(Descriptor::Hog { cell: 4, bins: 9 }),
+            _ => None
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match *self {
+            Descriptor::Zoning { cols, rows } => cols * rows,
+            Descriptor::Projections { bins } => 2 * bins,
+            Descriptor::Hog { cell, bins } => (GLYPH_WIDTH / cell) * (GLYPH_HEIGHT / cell) * bins
+        }
+    }
+
+    pub fn compute(&self, g: &Glyph, out: &mut Vec<f32>) {
+        match *self {
+            Descriptor::Zoning { cols, rows } => zoning(g, cols, rows, out),
+            Descriptor::Projections { bins } => projections(g, bins, out),
+            Descriptor::Hog { cell, bins } => hog(g, cell, bins, out)
+        }
+    }
+}
+
+/// Parses comma separated list of descriptor names, e.g. `zoning,hog`.
+pub fn parse_descriptors(s: &str) -> Result<Vec<Descriptor>, String> {
+    let mut res = vec![];
+    for name in s.split(',').map(|n| n.trim()).filter(|n| n.len() > 0) {
+        match Descriptor::from_name(name) {
+            Some(d) => res.push(d),
+            None => return Err(format!("unknown descriptor `{}`", name))
+        }
+    }
+    if res.len() == 0 {
+        return Err("no descriptors".to_string());
+    }
+    Ok(res)
+}
+
+/// Mean of glyph pixels in `x0..x1, y0..y1`.
+fn mean(g: &Glyph, x0: usize, x1: usize, y0: usize, y1: usize) -> f32 {
+    let mut sum = 0.0f32;
+    for y in y0..y1 {
+        for x in x0..x1 {
+            sum += g.get(x, y);
+        }
+    }
+    let n = (x1 - x0) * (y1 - y0);
+    if n > 0 { sum / (n as f32) } else { 0.0f32 }
+}
+
+fn zoning(g: &Glyph, cols: usize, rows: usize, out: &mut Vec<f32>) {
+    for r in 0..rows {
+        for c in 0..cols {
+            out.push(mean(
+                g,
+                c * GLYPH_WIDTH / cols, (c + 1) * GLYPH_WIDTH / cols,
+                r * GLYPH_HEIGHT / rows, (r + 1) * GLYPH_HEIGHT / rows
+            ));
+        }
+    }
+}
+
+fn projections(g: &Glyph, bins: usize, out: &mut Vec<f32>) {
+    for b in 0..bins {
+        out.push(mean(g, 0, GLYPH_WIDTH, b * GLYPH_HEIGHT / bins, (b + 1) * GLYPH_HEIGHT / bins));
+    }
+    for b in 0..bins {
+        out.push(mean(g, b * GLYPH_WIDTH / bins, (b + 1) * GLYPH_WIDTH / bins, 0, GLYPH_HEIGHT));
+    }
+}
+
+fn hog(g: &Glyph, cell: usize, bins: usize, out: &mut Vec<f32>) {
+    let at = |x: i32, y: i32| -> f32 {
+        if x < 0 || y < 0 || x >= GLYPH_WIDTH as i32 || y >= GLYPH_HEIGHT as i32 {
+            0.0f32
+        } else {
+            g.get(x as usize, y as usize)
+        }
+    };
+
+    for cy in 0..(GLYPH_HEIGHT / cell) {
+        for cx in 0..(GLYPH_WIDTH / cell) {
+            let mut hist = vec![0.0f32; bins];
+
+            for y in (cy * cell)..((cy + 1) * cell) {
+                for x in (cx * cell)..((cx + 1) * cell) {
+                    let (xi, yi) = (x as i32, y as i32);
+                    let dx = at(xi + 1, yi) - at(xi - 1, yi);
+                    let dy = at(xi, yi + 1) - at(xi, yi - 1);
+                    let magnitude = (dx * dx + dy * dy).sqrt();
+                    if magnitude == 0.0f32 {
+                        continue;
+                    }
+
+                    // unsigned orientation in [0, pi)
+                    let mut angle = dy.atan2(dx);
+                    if angle < 0.0f32 {
+                        angle += PI;
+                    }
+                    let bin = ((angle / PI * (bins as f32)) as usize) % bins;
+                    hist[bin] += magnitude;
+                }
+            }
+
+            let norm = hist.iter().fold(0.0f32, |a, v| a + v * v).sqrt();
+            for v in hist {
+                out.push(if norm > 0.0f32 { v / norm } else { 0.0f32 });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Image;
+    use ml::ocr::glyph::{Glyph, GLYPH_WIDTH, GLYPH_HEIGHT};
+
+    fn left_half() -> Glyph {
+        let mut data = vec![0.0f32; GLYPH_WIDTH * GLYPH_HEIGHT];
+        for y in 0..GLYPH_HEIGHT {
+            for x in 0..(GLYPH_WIDTH / 2) {
+                data[y * GLYPH_WIDTH + x] = 1.0f32;
+            }
+        }
+        Glyph { pixels: Image::from_data(data, GLYPH_WIDTH, GLYPH_HEIGHT) }
+    }
+
+    #[test]
+    fn lengths_match() {
+        let g = left_half();
+        for d in parse_descriptors("zoning,projections,hog").unwrap() {
+            let mut out = vec![];
+            d.compute(&g, &mut out);
+            assert_eq!(out.len(), d.len());
+        }
+    }
+
+    #[test]
+    fn zoning_and_projections() {
+        let g = left_half();
+
+        let mut z = vec![];
+        Descriptor::Zoning { cols: 2, rows: 1 }.compute(&g, &mut z);
+        assert_eq!(z, vec![1.0f32, 0.0f32]);
+
+        let mut p = vec![];
+        Descriptor::Projections { bins: 2 }.compute(&g, &mut p);
+        assert_eq!(p, vec![0.5f32, 0.5f32, 1.0f32, 0.0f32]);
+    }
+
+    #[test]
+    fn hog_of_vertical_edge() {
+        let mut out = vec![];
+        Descriptor::Hog { cell: 8, bins: 4 }.compute(&left_half(), &mut out);
+
+        // away from the top and bottom borders the only edges are vertical,
+        // so all gradients of the middle row of cells are horizontal
+        assert_eq!(&out[8..12], &[1.0f32, 0.0f32, 0.0f32, 0.0f32]);
+        assert_eq!(&out[12..16], &[1.0f32, 0.0f32, 0.0f32, 0.0f32]);
+    }
+}
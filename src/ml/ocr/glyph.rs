@@ -0,0 +1,190 @@
+use std::cmp;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use pd_image::ImageError;
+
+use image::Image;
+use image::io::load_from_file;
+use structures::Point;
+use extract::cser::feature::mask::RegionMask;
+
+pub static GLYPH_WIDTH: usize = 16;
+pub static GLYPH_HEIGHT: usize = 24;
+
+// every glyph pixel is sampled SUBSAMPLES x SUBSAMPLES times
+static SUBSAMPLES: usize = 4;
+
+/// Character bitmap scaled to `GLYPH_WIDTH x GLYPH_HEIGHT` preserving its
+/// aspect ratio and centered. Pixel values are ink coverage in `0..1`.
+#[derive(Clone, PartialEq)]
+pub struct Glyph {
+    pub pixels: Image<f32>
+}
+
+impl Glyph {
+    /// Glyph of the `true` pixels of `mask`.
+    pub fn from_mask(mask: &Image<bool>) -> Glyph {
+        let mut pixels = Image::from_data(vec![0.0f32; GLYPH_WIDTH * GLYPH_HEIGHT], GLYPH_WIDTH, GLYPH_HEIGHT);
+
+        let ink: Vec<(usize, usize)> = (0..mask.height())
+            .flat_map(|y| (0..mask.width()).map(move |x| (x, y)))
+            .filter(|&(x, y)| mask[(x, y)])
+            .collect();
+        if ink.len() == 0 {
+            return Glyph { pixels: pixels };
+        }
+
+        let (x0, y0) = ink.iter().fold((usize::max_value(), usize::max_value()), |a, p| (cmp::min(a.0, p.0), cmp::min(a.1, p.1)));
+        let (x1, y1) = ink.iter().fold((0, 0), |a, p| (cmp::max(a.0, p.0), cmp::max(a.1, p.1)));
+        let (w, h) = ((x1 - x0 + 1) as f32, (y1 - y0 + 1) as f32);
+
+        let scale = f32::min((GLYPH_WIDTH as f32) / w, (GLYPH_HEIGHT as f32) / h);
+        let off_x = ((GLYPH_WIDTH as f32) - w * scale) / 2.0f32;
+        let off_y = ((GLYPH_HEIGHT as f32) - h * scale) / 2.0f32;
+        let step = 1.0f32 / (SUBSAMPLES as f32);
+
+        for gy in 0..GLYPH_HEIGHT {
+            for gx in 0..GLYPH_WIDTH {
+                let mut hits = 0;
+                for sy in 0..SUBSAMPLES {
+                    for sx in 0..SUBSAMPLES {
+                        let mx = ((gx as f32) + ((sx as f32) + 0.5f32) * step - off_x) / scale;
+                        let my = ((gy as f32) + ((sy as f32) + 0.5f32) * step - off_y) / scale;
+                        if mx >= 0.0f32 && my >= 0.0f32 && mx < w && my < h &&
+                           mask[(x0 + mx as usize, y0 + my as usize)] {
+                            hits += 1;
+                        }
+                    }
+                }
+                pixels.set_pixel(gx as i32, gy as i32, (hits as f32) / ((SUBSAMPLES * SUBSAMPLES) as f32));
+            }
+        }
+
+        Glyph { pixels: pixels }
+    }
+
+    /// Glyph of a detected region.
+    pub fn from_points(points: &[Point]) -> Glyph {
+        Glyph::from_mask(&RegionMask::from_points(points, 0).mask)
+    }
+
+    /// Glyph of a grayscale crop with a dark character on light background,
+    /// binarized with Otsu's threshold.
+    pub fn from_crop(img: &Image<u8>) -> Glyph {
+        let t = otsu_threshold(img);
+        Glyph::from_mask(&img.map(|v| *v <= t))
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> f32 {
+        self.pixels[(x, y)]
+    }
+}
+
+/// Threshold maximizing between-class variance of the intensity histogram.
+pub fn otsu_threshold(img: &Image<u8>) -> u8 {
+    let mut hist = [0usize; 256];
+    for v in img.data() {
+        hist[*v as usize] += 1;
+    }
+
+    let total = img.data().len() as f64;
+    let sum = (0..256).fold(0.0f64, |a, i| a + (i as f64) * (hist[i] as f64));
+
+    let (mut best, mut best_var) = (0u8, -1.0f64);
+    let (mut w0, mut sum0) = (0.0f64, 0.0f64);
+    for t in 0..256 {
+        w0 += hist[t] as f64;
+        sum0 += (t as f64) * (hist[t] as f64);
+        let w1 = total - w0;
+        if w0 == 0.0 || w1 == 0.0 {
+            continue;
+        }
+
+        let (m0, m1) = (sum0 / w0, (sum - sum0) / w1);
+        let var = w0 * w1 * (m0 - m1) * (m0 - m1);
+        if var > best_var {
+            best = t as u8;
+            best_var = var;
+        }
+    }
+    best
+}
+
+#[derive(Debug)]
+pub enum GlyphLoadError {
+    IoError(io::Error),
+    ImageError(String, ImageError),
+    /// Class directories must be named by a single character.
+    BadClassName(String)
+}
+
+/// Loads labeled glyph crops from `dir` that has a subdirectory per
+/// character, e.g. `dir/A/0001.png`.
+pub fn load_glyph_dir(dir: &str) -> Result<Vec<(Glyph, char)>, GlyphLoadError> {
+    let mut res = vec![];
+
+    for class_dir in try!(fs::read_dir(dir).map_err(|e| GlyphLoadError::IoError(e))) {
+        let class_dir = try!(class_dir.map_err(|e| GlyphLoadError::IoError(e))).path();
+        if !class_dir.is_dir() {
+            continue;
+        }
+
+        let name = class_dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or(String::new());
+        let mut chars = name.chars();
+        let c = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _ => return Err(GlyphLoadError::BadClassName(name.clone()))
+        };
+
+        for file in try!(fs::read_dir(&class_dir).map_err(|e| GlyphLoadError::IoError(e))) {
+            let path = try!(file.map_err(|e| GlyphLoadError::IoError(e))).path();
+            if !is_png(&path) {
+                continue;
+            }
+
+            let path = path.to_string_lossy().into_owned();
+            let img = try!(load_from_file(&path).map_err(|e| GlyphLoadError::ImageError(path.clone(), e)));
+            res.push((Glyph::from_crop(&img), c));
+        }
+    }
+
+    Ok(res)
+}
+
+pub fn is_png(path: &Path) -> bool {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase() == "png").unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Image;
+
+    #[test]
+    fn scaled_and_centered() {
+        // 1x3 bar is scaled 8 times to fill the glyph height
+        let mask = Image::from_data(vec![
+            false, false, false,
+            false, true,  false,
+            false, true,  false,
+            false, true,  false,
+        ], 3, 4);
+
+        let g = Glyph::from_mask(&mask);
+
+        assert_eq!(g.get(0, 0), 0.0f32);
+        assert_eq!(g.get(3, 12), 0.0f32);
+        assert_eq!(g.get(4, 0), 1.0f32);
+        assert_eq!(g.get(11, 23), 1.0f32);
+        assert_eq!(g.get(12, 5), 0.0f32);
+    }
+
+    #[test]
+    fn otsu_separates_modes() {
+        let img = Image::from_data(vec![10u8, 12, 11, 200, 210, 205], 6, 1);
+        let t = otsu_threshold(&img);
+        assert!(t >= 12 && t < 200);
+    }
+}
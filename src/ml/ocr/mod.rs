@@ -0,0 +1,9 @@
+pub mod glyph;
+pub mod descriptor;
+pub mod recognizer;
+pub mod template;
+
+pub use self::glyph::{Glyph, GLYPH_WIDTH, GLYPH_HEIGHT, load_glyph_dir};
+pub use self::descriptor::{Descriptor, DEFAULT_DESCRIPTORS, parse_descriptors};
+pub use self::recognizer::{CharRecognizer, Recognition};
+pub use self::template::{TemplateMatcher, TemplateMatch};
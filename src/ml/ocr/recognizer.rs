@@ -0,0 +1,186 @@
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::prelude::*;
+
+use ml::classifier::{Classifier, Normalized};
+use ml::model::{ClassifierModel, ModelError, ModelResult, write_versioned, read_versioned};
+use ml::trainer::{Trainer, ModelTrainer};
+use ml::online::OnlineUpdate;
+use super::glyph::Glyph;
+use super::descriptor::Descriptor;
+
+static MAGIC: &'static str = "nprs-ocr";
+
+/// Version of the on-disk character recognizer format.
+pub static OCR_VERSION: u32 = 2;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Recognition {
+    pub character: char,
+    /// Probability of the character according to the classifier.
+    pub confidence: f32
+}
+
+/// Classifier of glyph bitmaps into characters of its alphabet.
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+pub struct CharRecognizer {
+    alphabet: Vec<char>,
+    descriptors: Vec<Descriptor>,
+    classifier: Normalized<ClassifierModel>
+}
+
+/// Concatenated descriptors of a glyph.
+pub fn describe(descriptors: &[Descriptor], g: &Glyph) -> Vec<f32> {
+    let mut out = vec![];
+    for d in descriptors {
+        d.compute(g, &mut out);
+    }
+    out
+}
+
+impl CharRecognizer {
+    /// Trains recognizer of characters found among `samples`. The trainer
+    /// must produce multi-class classifiers if there are more than two
+    /// characters.
+    pub fn train(samples: &[(Glyph, char)], descriptors: Vec<Descriptor>, trainer: &ModelTrainer) -> CharRecognizer {
+        let mut alphabet: Vec<char> = samples.iter().map(|s| s.1).collect();
+        alphabet.sort();
+        alphabet.dedup();
+
+        let rows: Vec<Vec<f32>> = samples.iter().map(|s| describe(&descriptors, &s.0)).collect();
+        let labels: Vec<usize> = samples.iter()
+            .map(|s| alphabet.binary_search(&s.1).unwrap())
+            .collect();
+
+        let classifier = trainer.train(&rows, &labels);
+        assert!(
+            classifier.num_classes() >= alphabet.len(),
+            "{:?} can't distinguish {} characters", trainer.algorithm, alphabet.len()
+        );
+
+        CharRecognizer {
+            alphabet: alphabet,
+            descriptors: descriptors,
+            classifier: classifier
+        }
+    }
+
+    pub fn alphabet<'a>(&'a self) -> &'a [char] {
+        &self.alphabet[..]
+    }
+
+    pub fn descriptors<'a>(&'a self) -> &'a [Descriptor] {
+        &self.descriptors[..]
+    }
+
+    /// All characters of the alphabet with their probabilities, the most
+    /// probable first.
+    pub fn candidates(&self, g: &Glyph) -> Vec<Recognition> {
+        let mut p = vec![];
+        self.classifier.probabilities(&describe(&self.descriptors, g), &mut p);
+
+        let mut res: Vec<Recognition> = self.alphabet.iter().zip(p.iter())
+            .map(|(c, p)| Recognition { character: *c, confidence: *p })
+            .collect();
+        res.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(Ordering::Equal));
+        res
+    }
+
+    pub fn recognize(&self, g: &Glyph) -> Recognition {
+        self.candidates(g)[0]
+    }
+
+    /// Learns from a glyph of character `c` confirmed by an operator.
+    /// Returns `false` if `c` isn't in the alphabet or the classifier has
+    /// to be retrained to learn it.
+    pub fn update(&mut self, g: &Glyph, c: char, learning_rate: f32) -> bool {
+        match self.alphabet.binary_search(&c) {
+            Ok(label) => self.classifier.update(&describe(&self.descriptors, g), label, learning_rate),
+            Err(_) => false
+        }
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> ModelResult<()> {
+        write_versioned(MAGIC, OCR_VERSION, self, w)
+    }
+
+    pub fn read<R: Read>(r: &mut R) -> ModelResult<CharRecognizer> {
+        read_versioned(MAGIC, OCR_VERSION, r)
+    }
+
+    pub fn save(&self, path: &str) -> ModelResult<()> {
+        let mut f = try!(File::create(path).map_err(|e| ModelError::IoError(e)));
+        self.write(&mut f)
+    }
+
+    pub fn load(path: &str) -> ModelResult<CharRecognizer> {
+        let mut f = try!(File::open(path).map_err(|e| ModelError::IoError(e)));
+        CharRecognizer::read(&mut f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use ml::ocr::glyph::Glyph;
+    use ml::ocr::template::glyph_from_rows;
+    use ml::ocr::descriptor::parse_descriptors;
+    use ml::trainer::{ModelTrainer, Algorithm};
+    use ml::normalize::StepSpec;
+
+    fn samples() -> Vec<(Glyph, char)> {
+        let one = glyph_from_rows(&["..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###."]);
+        let seven = glyph_from_rows(&["#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#..."]);
+        let zero = glyph_from_rows(&[".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."]);
+
+        let mut res = vec![];
+        for _ in 0..10 {
+            res.push((one.clone(), '1'));
+            res.push((seven.clone(), '7'));
+            res.push((zero.clone(), '0'));
+        }
+        res
+    }
+
+    fn recognizer() -> CharRecognizer {
+        let trainer = ModelTrainer::new(vec![StepSpec::Standardize], Algorithm::from_name("forest").unwrap());
+        CharRecognizer::train(&samples(), parse_descriptors("zoning,projections,hog").unwrap(), &trainer)
+    }
+
+    #[test]
+    fn recognizes_training_glyphs() {
+        let r = recognizer();
+        assert_eq!(r.alphabet(), &['0', '1', '7']);
+
+        for &(ref g, c) in samples().iter().take(3) {
+            let res = r.recognize(g);
+            assert_eq!(res.character, c);
+            assert!(res.confidence > 0.5f32);
+        }
+    }
+
+    #[test]
+    fn write_read_roundtrip() {
+        let r = recognizer();
+        let mut buf = vec![];
+        r.write(&mut buf).unwrap();
+
+        let loaded = CharRecognizer::read(&mut Cursor::new(buf)).unwrap();
+
+        let g = &samples()[1].0;
+        assert_eq!(loaded.candidates(g), r.candidates(g));
+    }
+
+    #[test]
+    fn update_with_mlp() {
+        let trainer = ModelTrainer::new(vec![StepSpec::Standardize], Algorithm::from_name("mlp").unwrap());
+        let mut r = CharRecognizer::train(&samples(), parse_descriptors("zoning").unwrap(), &trainer);
+        let one = samples()[0].0.clone();
+
+        assert!(!r.update(&one, 'X', 0.01f32));
+        assert!(r.update(&one, '1', 0.01f32));
+        assert!(!recognizer().update(&one, '1', 0.01f32));
+    }
+}
@@ -0,0 +1,235 @@
+use std::cmp::Ordering;
+use std::fs;
+
+use image::Image;
+use image::io::load_from_file;
+use super::glyph::{Glyph, GlyphLoadError, is_png};
+use super::recognizer::Recognition;
+
+/// 5x7 bitmaps of digits and latin capitals, `#` is ink.
+static BUILTIN_FONT: [(char, [&'static str; 7]); 36] = [
+    ('0', [".###.", "#...#", "#..##", "#.#.#", "##..#", "#...#", ".###."]),
+    ('1', ["..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###."]),
+    ('2', [".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####"]),
+    ('3', ["#####", "...#.", "..#..", "...#.", "....#", "#...#", ".###."]),
+    ('4', ["...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#."]),
+    ('5', ["#####", "#....", "####.", "....#", "....#", "#...#", ".###."]),
+    ('6', ["..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###."]),
+    ('7', ["#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#..."]),
+    ('8', [".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###."]),
+    ('9', [".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##.."]),
+    ('A', [".###.", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"]),
+    ('B', ["####.", "#...#", "#...#", "####.", "#...#", "#...#", "####."]),
+    ('C', [".###.", "#...#", "#....", "#....", "#....", "#...#", ".###."]),
+    ('D', ["###..", "#..#.", "#...#", "#...#", "#...#", "#..#.", "###.."]),
+    ('E', ["#####", "#....", "#....", "####.", "#....", "#....", "#####"]),
+    ('F', ["#####", "#....", "#....", "####.", "#....", "#....", "#...."]),
+    ('G', [".###.", "#...#", "#....", "#.###", "#...#", "#...#", ".####"]),
+    ('H', ["#...#", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"]),
+    ('I', [".###.", "..#..", "..#..", "..#..", "..#..", "..#..", ".###."]),
+    ('J', ["..###", "...#.", "...#.", "...#.", "...#.", "#..#.", ".##.."]),
+    ('K', ["#...#", "#..#.", "#.#..", "##...", "#.#..", "#..#.", "#...#"]),
+    ('L', ["#....", "#....", "#....", "#....", "#....", "#....", "#####"]),
+    ('M', ["#...#", "##.##", "#.#.#", "#.#.#", "#...#", "#...#", "#...#"]),
+    ('N', ["#...#", "#...#", "##..#", "#.#.#", "#..##", "#...#", "#...#"]),
+    ('O', [".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."]),
+    ('P', ["####.", "#...#", "#...#", "####.", "#....", "#....", "#...."]),
+    ('Q', [".###.", "#...#", "#...#", "#...#", "#.#.#", "#..#.", ".##.#"]),
+    ('R', ["####.", "#...#", "#...#", "####.", "#.#..", "#..#.", "#...#"]),
+    ('S', [".####", "#....", "#....", ".###.", "....#", "....#", "####."]),
+    ('T', ["#####", "..#..", "..#..", "..#..", "..#..", "..#..", "..#.."]),
+    ('U', ["#...#", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."]),
+    ('V', ["#...#", "#...#", "#...#", "#...#", "#...#", ".#.#.", "..#.."]),
+    ('W', ["#...#", "#...#", "#...#", "#.#.#", "#.#.#", "#.#.#", ".#.#."]),
+    ('X', ["#...#", "#...#", ".#.#.", "..#..", ".#.#.", "#...#", "#...#"]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#..", "..#.."]),
+    ('Z', ["#####", "....#", "...#.", "..#..", ".#...", "#....", "#####"]),
+];
+
+/// Glyph of a bitmap given as rows of `#` and `.`.
+pub fn glyph_from_rows(rows: &[&str]) -> Glyph {
+    let data: Vec<bool> = rows.iter().flat_map(|r| r.chars().map(|c| c == '#')).collect();
+    Glyph::from_mask(&Image::from_data(data, rows[0].len(), rows.len()))
+}
+
+/// Zero mean normalized cross-correlation of two glyphs in `-1..1`.
+pub fn ncc(a: &Glyph, b: &Glyph) -> f32 {
+    let (a, b) = (a.pixels.data(), b.pixels.data());
+    let n = a.len() as f32;
+    let mean_a = a.iter().fold(0.0f32, |s, v| s + v) / n;
+    let mean_b = b.iter().fold(0.0f32, |s, v| s + v) / n;
+
+    let (mut ab, mut aa, mut bb) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b.iter()) {
+        let (x, y) = (x - mean_a, y - mean_b);
+        ab += x * y;
+        aa += x * x;
+        bb += y * y;
+    }
+
+    if aa > 0.0f32 && bb > 0.0f32 { ab / (aa * bb).sqrt() } else { 0.0f32 }
+}
+
+/// Best and second best character of a template match. Confidences are
+/// correlation coefficients.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TemplateMatch {
+    pub best: Recognition,
+    pub runner_up: Option<Recognition>
+}
+
+/// Recognizes glyphs by correlation with character templates, doesn't need
+/// any training. A character may have several templates.
+#[derive(Clone)]
+pub struct TemplateMatcher {
+    templates: Vec<(char, Glyph)>
+}
+
+impl TemplateMatcher {
+    pub fn new(templates: Vec<(char, Glyph)>) -> TemplateMatcher {
+        TemplateMatcher { templates: templates }
+    }
+
+    /// Templates of the built-in 5x7 font of digits and latin capitals.
+    pub fn builtin() -> TemplateMatcher {
+        TemplateMatcher::new(BUILTIN_FONT.iter().map(|&(c, ref rows)| (c, glyph_from_rows(rows))).collect())
+    }
+
+    /// Loads templates from PNG files of `dir`. Character of a template is
+    /// the first character of its file name, so `A.png` and `A_narrow.png`
+    /// are both templates of `A`.
+    pub fn from_dir(dir: &str) -> Result<TemplateMatcher, GlyphLoadError> {
+        let mut templates = vec![];
+
+        for file in try!(fs::read_dir(dir).map_err(|e| GlyphLoadError::IoError(e))) {
+            let path = try!(file.map_err(|e| GlyphLoadError::IoError(e))).path();
+            if !is_png(&path) {
+                continue;
+            }
+
+            let c = match path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.chars().next()) {
+                Some(c) => c,
+                None => continue
+            };
+
+            let path = path.to_string_lossy().into_owned();
+            let img = try!(load_from_file(&path).map_err(|e| GlyphLoadError::ImageError(path.clone(), e)));
+            templates.push((c, Glyph::from_crop(&img)));
+        }
+
+        Ok(TemplateMatcher::new(templates))
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    /// Best correlation of every character, the best first.
+    pub fn scores(&self, g: &Glyph) -> Vec<Recognition> {
+        let mut res: Vec<Recognition> = vec![];
+        for &(c, ref t) in self.templates.iter() {
+            let score = ncc(g, t);
+            let known = res.iter().position(|r| r.character == c);
+            match known {
+                Some(i) => res[i].confidence = res[i].confidence.max(score),
+                None => res.push(Recognition { character: c, confidence: score })
+            }
+        }
+
+        res.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(Ordering::Equal));
+        res
+    }
+
+    /// `None` if there are no templates.
+    pub fn recognize(&self, g: &Glyph) -> Option<TemplateMatch> {
+        let scores = self.scores(g);
+        scores.first().map(|best| TemplateMatch {
+            best: *best,
+            runner_up: scores.get(1).cloned()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+    use super::BUILTIN_FONT;
+    use image::Image;
+    use image::io::save_to_file;
+
+    #[test]
+    fn builtin_recognizes_itself() {
+        let matcher = TemplateMatcher::builtin();
+        assert_eq!(matcher.len(), 36);
+
+        for &(c, ref rows) in BUILTIN_FONT.iter() {
+            let m = matcher.recognize(&glyph_from_rows(rows)).unwrap();
+            assert_eq!(m.best.character, c);
+            assert!((m.best.confidence - 1.0f32).abs() < 1e-4);
+            assert!(m.runner_up.unwrap().confidence < m.best.confidence);
+        }
+    }
+
+    #[test]
+    fn thick_glyph_matches() {
+        // 7 drawn at twice the size of the template
+        let seven = glyph_from_rows(&[
+            "##########",
+            "##########",
+            "........##",
+            "........##",
+            "......##..",
+            "......##..",
+            "....##....",
+            "....##....",
+            "..##......",
+            "..##......",
+            "..##......",
+            "..##......",
+            "..##......",
+            "..##......",
+        ]);
+
+        let m = TemplateMatcher::builtin().recognize(&seven).unwrap();
+        assert_eq!(m.best.character, '7');
+    }
+
+    #[test]
+    fn empty_matcher() {
+        assert_eq!(TemplateMatcher::new(vec![]).recognize(&glyph_from_rows(&["#"])), None);
+    }
+
+    #[test]
+    fn from_dir_loads_png_templates() {
+        let dir = env::temp_dir().join("nprs-template-matcher-test");
+        fs::create_dir_all(&dir).unwrap();
+
+        // dark glyphs on light background with a margin of 2 pixels
+        for &(c, ref rows) in BUILTIN_FONT.iter().take(3) {
+            let (w, h) = (rows[0].len() + 4, rows.len() + 4);
+            let mut img: Image<u8> = Image::from_data(vec![255; w * h], w, h);
+            for (y, row) in rows.iter().enumerate() {
+                for (x, v) in row.chars().enumerate() {
+                    if v == '#' {
+                        img.set_pixel(x as i32 + 2, y as i32 + 2, 0);
+                    }
+                }
+            }
+
+            let path = dir.join(format!("{}_template.png", c));
+            save_to_file(&path.to_string_lossy(), &img).unwrap();
+        }
+        fs::File::create(dir.join("README.txt")).unwrap();
+
+        let matcher = TemplateMatcher::from_dir(&dir.to_string_lossy()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(matcher.len(), 3);
+        for &(c, ref rows) in BUILTIN_FONT.iter().take(3) {
+            assert_eq!(matcher.recognize(&glyph_from_rows(rows)).unwrap().best.character, c);
+        }
+    }
+}
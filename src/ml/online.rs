@@ -0,0 +1,182 @@
+use super::classifier::{Classifier, Normalized};
+use super::model::{Model, ClassifierModel};
+use super::dataset::{Dataset, Sample};
+use super::trainer::Trainer;
+
+/// Classifier that can learn from single labeled samples, e.g. regions
+/// confirmed or rejected by an operator.
+pub trait OnlineUpdate {
+    /// Moves the classifier towards predicting `label` for feature vector
+    /// `x`. Returns `false` if the classifier can't be updated this way and
+    /// has to be retrained.
+    fn update(&mut self, x: &[f32], label: usize, learning_rate: f32) -> bool;
+}
+
+impl OnlineUpdate for ClassifierModel {
+    /// Logistic regression and MLP learn incrementally, ensembles of trees
+    /// and stumps don't.
+    fn update(&mut self, x: &[f32], label: usize, learning_rate: f32) -> bool {
+        match *self {
+            ClassifierModel::Logistic(ref mut c) => c.update(x, label, learning_rate),
+            ClassifierModel::Mlp(ref mut c) => c.update(x, label, learning_rate),
+            ClassifierModel::AdaBoost(_) | ClassifierModel::RandomForest(_) => false
+        }
+    }
+}
+
+impl<C: Classifier + OnlineUpdate> OnlineUpdate for Normalized<C> {
+    /// Normalization stays fixed, `x` is normalized before the update.
+    fn update(&mut self, x: &[f32], label: usize, learning_rate: f32) -> bool {
+        let mut nx = x.to_vec();
+        self.normalizer.apply(&mut nx);
+        self.classifier.update(&nx, label, learning_rate)
+    }
+}
+
+impl OnlineUpdate for Model {
+    /// Calibration isn't refitted, so calibrated scores drift away from
+    /// the truth after many updates.
+    fn update(&mut self, x: &[f32], label: usize, learning_rate: f32) -> bool {
+        self.classifier.update(x, label, learning_rate)
+    }
+}
+
+/// Corrected samples waiting to be added to the training dataset. A
+/// corrected sample replaces a sample of the same region in the dataset,
+/// so that the operator's label wins over the one from ground truth.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedbackQueue {
+    pending: Vec<Sample>,
+    /// Number of pending samples that triggers retraining.
+    pub retrain_after: usize
+}
+
+impl FeedbackQueue {
+    pub fn new(retrain_after: usize) -> FeedbackQueue {
+        FeedbackQueue {
+            pending: vec![],
+            retrain_after: retrain_after
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Queues a corrected sample. A newer correction of the same region
+    /// replaces the older one.
+    pub fn push(&mut self, sample: Sample) {
+        self.pending.retain(|s| !s.same_region(&sample));
+        self.pending.push(sample);
+    }
+
+    /// Queues `sample` and applies it to `classifier` right away. Returns
+    /// whether the classifier was updated.
+    pub fn push_and_update<C: OnlineUpdate>(&mut self, classifier: &mut C, sample: Sample, learning_rate: f32) -> bool {
+        let updated = classifier.update(&sample.features, sample.label, learning_rate);
+        self.push(sample);
+        updated
+    }
+
+    pub fn should_retrain(&self) -> bool {
+        self.pending.len() > 0 && self.pending.len() >= self.retrain_after
+    }
+
+    /// Moves pending samples into `dataset`. Returns number of dataset
+    /// samples that were relabeled.
+    pub fn flush_into(&mut self, dataset: &mut Dataset) -> usize {
+        let mut relabeled = 0;
+        for sample in self.pending.drain(..) {
+            let known = dataset.samples.iter().position(|s| s.same_region(&sample));
+            match known {
+                Some(i) => {
+                    dataset.samples[i] = sample;
+                    relabeled += 1;
+                },
+                None => dataset.push(sample)
+            }
+        }
+        relabeled
+    }
+
+    /// Flushes pending samples into `dataset` and retrains on it once
+    /// enough of them are queued.
+    pub fn retrain_if_needed<T: Trainer>(&mut self, dataset: &mut Dataset, trainer: &T) -> Option<T::Output> {
+        if !self.should_retrain() {
+            return None;
+        }
+        self.flush_into(dataset);
+        Some(trainer.train(&dataset.rows(), &dataset.labels()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use structures::{Point, Rect};
+    use extract::cser::feature::{Schema, AspectRatio};
+    use ml::classifier::{Classifier, BACKGROUND, CHARACTER};
+    use ml::dataset::{Dataset, Sample};
+    use ml::logistic::LogisticRegression;
+    use ml::mlp::Mlp;
+    use ml::model::ClassifierModel;
+    use ml::normalize::StepSpec;
+    use ml::trainer::{Trainer, ModelTrainer, Algorithm};
+    use super::*;
+
+    fn sample(x: f32, label: usize) -> Sample {
+        Sample {
+            features: vec![x],
+            label: label,
+            image: "001.jpg".to_string(),
+            bounds: Rect(Point { x: (x * 10.0f32) as i32, y: 0 }, Point { x: 20, y: 20 }),
+            thres: 100
+        }
+    }
+
+    #[test]
+    fn updates_move_towards_label() {
+        let mut logistic = ClassifierModel::Logistic(LogisticRegression::new(vec![0.0f32], 0.0f32));
+        let mut mlp = ClassifierModel::Mlp(Mlp::new(&[1, 4, 2], 3));
+
+        for c in [&mut logistic, &mut mlp].iter_mut() {
+            let before = c.score(&[1.0f32]);
+            for _ in 0..20 {
+                assert!(c.update(&[1.0f32], CHARACTER, 0.1f32));
+            }
+            assert!(c.score(&[1.0f32]) > before);
+        }
+    }
+
+    #[test]
+    fn forest_needs_retraining() {
+        let trainer = ModelTrainer::new(vec![], Algorithm::from_name("forest").unwrap());
+        let rows = vec![vec![0.0f32], vec![1.0f32]];
+        let mut forest = trainer.train(&rows, &[BACKGROUND, CHARACTER]);
+
+        assert!(!forest.update(&[0.0f32], CHARACTER, 0.1f32));
+    }
+
+    #[test]
+    fn corrections_relabel_dataset() {
+        let mut dataset = Dataset::new(Schema::of::<AspectRatio>());
+        for i in 0..4 {
+            dataset.push(sample(i as f32, if i < 2 { BACKGROUND } else { CHARACTER }));
+        }
+
+        let mut queue = FeedbackQueue::new(3);
+        queue.push(sample(1.0f32, CHARACTER));
+        queue.push(sample(5.0f32, BACKGROUND));
+        queue.push(sample(5.0f32, CHARACTER));
+        assert_eq!(queue.len(), 2);
+
+        let trainer = ModelTrainer::new(vec![StepSpec::Standardize], Algorithm::from_name("logistic").unwrap());
+        assert!(queue.retrain_if_needed(&mut dataset, &trainer).is_none());
+
+        queue.push(sample(6.0f32, CHARACTER));
+        assert!(queue.retrain_if_needed(&mut dataset, &trainer).is_some());
+
+        assert_eq!(queue.len(), 0);
+        assert_eq!(dataset.len(), 6);
+        assert_eq!(dataset.labels(), vec![BACKGROUND, CHARACTER, CHARACTER, CHARACTER, CHARACTER, CHARACTER]);
+    }
+}
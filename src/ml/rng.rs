@@ -0,0 +1,70 @@
+use std::f32;
+
+/// Small xorshift64* generator. Training has to be reproducible, so every
+/// randomized algorithm takes an explicit seed.
+#[derive(Debug, Clone)]
+pub struct XorShift {
+    state: u64
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> XorShift {
+        // zero state would produce only zeros
+        XorShift { state: if seed == 0 { 0x9E3779B97F4A7C15 } else { seed } }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    /// Uniformly distributed integer in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        debug_assert!(n > 0);
+        (self.next_u64() % (n as u64)) as usize
+    }
+
+    /// Uniformly distributed number in `[0, 1)`.
+    pub fn uniform(&mut self) -> f32 {
+        ((self.next_u64() >> 40) as f32) / ((1u64 << 24) as f32)
+    }
+
+    /// Normally distributed number with zero mean and unit variance.
+    pub fn normal(&mut self) -> f32 {
+        // Box-Muller transform, `1 - uniform` is never zero
+        let (u1, u2) = (1.0f32 - self.uniform(), self.uniform());
+        (-2.0f32 * u1.ln()).sqrt() * (2.0f32 * f32::consts::PI * u2).cos()
+    }
+
+    pub fn shuffle<T>(&mut self, xs: &mut [T]) {
+        for i in (1..xs.len()).rev() {
+            let j = self.below(i + 1);
+            xs.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reproducible() {
+        let (mut a, mut b) = (XorShift::new(42), XorShift::new(42));
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        let mut r = XorShift::new(1);
+        for _ in 0..1000 {
+            assert!(r.below(7) < 7);
+            let u = r.uniform();
+            assert!(u >= 0.0f32 && u < 1.0f32);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use super::classifier::{Classifier, Normalized, BACKGROUND, CHARACTER};
+use super::normalize::{Normalizer, StepSpec};
+use super::logistic::{LogisticRegression, LogisticParams};
+use super::adaboost::{AdaBoost, AdaBoostParams};
+use super::forest::{RandomForest, ForestParams};
+use super::mlp::{Mlp, MlpParams};
+use super::model::ClassifierModel;
+
+/// Fits a classifier on raw feature vectors. Everything fitted on data,
+/// including normalization, has to happen inside `train`, so that
+/// cross-validation doesn't leak statistics of held-out samples.
+pub trait Trainer {
+    type Output: Classifier;
+
+    fn train(&self, rows: &[Vec<f32>], labels: &[usize]) -> Self::Output;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Algorithm {
+    Logistic(LogisticParams),
+    AdaBoost(AdaBoostParams),
+    RandomForest(ForestParams),
+    Mlp(MlpParams)
+}
+
+impl Algorithm {
+    /// Algorithm with default parameters by its name: `logistic`, `adaboost`,
+    /// `forest` or `mlp`.
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        match name {
+            "logistic" => Some(Algorithm::Logistic(LogisticParams::default())),
+            "adaboost" => Some(Algorithm::AdaBoost(AdaBoostParams::default())),
+            "forest" => Some(Algorithm::RandomForest(ForestParams::default())),
+            "mlp" => Some(Algorithm::Mlp(MlpParams::default())),
+            _ => None
+        }
+    }
+
+    /// Whether the algorithm learns every class. Logistic regression and
+    /// AdaBoost only tell characters from background.
+    pub fn is_multiclass(&self) -> bool {
+        match *self {
+            Algorithm::RandomForest(_) | Algorithm::Mlp(_) => true,
+            Algorithm::Logistic(_) | Algorithm::AdaBoost(_) => false
+        }
+    }
+
+    pub fn train(&self, rows: &[Vec<f32>], labels: &[usize]) -> ClassifierModel {
+        match *self {
+            Algorithm::Logistic(ref p) => ClassifierModel::Logistic(LogisticRegression::train(rows, labels, p)),
+            Algorithm::AdaBoost(ref p) => ClassifierModel::AdaBoost(AdaBoost::train(rows, labels, p)),
+            Algorithm::RandomForest(ref p) => ClassifierModel::RandomForest(RandomForest::train(rows, labels, p)),
+            Algorithm::Mlp(ref p) => ClassifierModel::Mlp(Mlp::train(rows, labels, p))
+        }
+    }
+}
+
+/// Fits normalization steps and then the classifier on normalized rows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelTrainer {
+    pub normalization: Vec<StepSpec>,
+    pub algorithm: Algorithm
+}
+
+impl ModelTrainer {
+    pub fn new(normalization: Vec<StepSpec>, algorithm: Algorithm) -> ModelTrainer {
+        ModelTrainer {
+            normalization: normalization,
+            algorithm: algorithm
+        }
+    }
+}
+
+impl Trainer for ModelTrainer {
+    type Output = Normalized<ClassifierModel>;
+
+    /// Panics if a binary algorithm gets labels other than `BACKGROUND` and
+    /// `CHARACTER`, instead of silently merging the other classes.
+    fn train(&self, rows: &[Vec<f32>], labels: &[usize]) -> Normalized<ClassifierModel> {
+        assert!(
+            self.algorithm.is_multiclass() || labels.iter().all(|l| *l == BACKGROUND || *l == CHARACTER),
+            "binary classifier can't learn classes other than background and character, use forest or mlp"
+        );
+
+        let normalizer = Normalizer::fit(&self.normalization, rows);
+        let mut normalized = rows.to_vec();
+        normalizer.apply_all(&mut normalized);
+
+        Normalized {
+            normalizer: normalizer,
+            classifier: self.algorithm.train(&normalized, labels)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ml::classifier::{Classifier, BACKGROUND, CHARACTER, DIGIT};
+
+    #[test]
+    #[should_panic(expected = "binary classifier")]
+    fn binary_rejects_multiclass_labels() {
+        let trainer = ModelTrainer::new(vec![], Algorithm::from_name("logistic").unwrap());
+        trainer.train(&[vec![0.0f32], vec![1.0f32], vec![2.0f32]], &[BACKGROUND, CHARACTER, DIGIT]);
+    }
+
+    #[test]
+    fn multiclass_keeps_classes() {
+        let trainer = ModelTrainer::new(vec![], Algorithm::from_name("forest").unwrap());
+        let model = trainer.train(&[vec![0.0f32], vec![1.0f32], vec![2.0f32]], &[BACKGROUND, CHARACTER, DIGIT]);
+
+        assert_eq!(model.num_classes(), 3);
+    }
+}
@@ -0,0 +1,83 @@
+extern crate nprs;
+extern crate stopwatch;
+
+use std::env;
+
+use stopwatch::Stopwatch;
+
+use nprs::ml::{ModelTrainer, Algorithm, cross_validate};
+use nprs::ml::normalize::StepSpec;
+use nprs::ml::ocr::{CharRecognizer, DEFAULT_DESCRIPTORS, parse_descriptors, load_glyph_dir};
+use nprs::ml::ocr::recognizer::describe;
+
+static USAGE: &'static str =
+    "usage: nprs-ocr-train <glyph directory> <model file> [--descriptors <descriptors>] \
+     [--classifier forest|mlp] [--folds <k>]";
+
+/// Seed of the fold assignment, fixed so that reports are comparable.
+static FOLDS_SEED: u64 = 1;
+
+struct Args {
+    glyph_dir: String,
+    model_file: String,
+    descriptors: String,
+    classifier: String,
+    folds: Option<usize>
+}
+
+fn parse_args() -> Args {
+    let mut args = env::args().skip(1);
+    let glyph_dir = args.next().expect(USAGE);
+    let model_file = args.next().expect(USAGE);
+    let mut res = Args {
+        glyph_dir: glyph_dir,
+        model_file: model_file,
+        descriptors: DEFAULT_DESCRIPTORS.to_string(),
+        classifier: "forest".to_string(),
+        folds: None
+    };
+
+    while let Some(flag) = args.next() {
+        let value = args.next().expect(USAGE);
+        match &flag[..] {
+            "--descriptors" => res.descriptors = value,
+            "--classifier" => res.classifier = value,
+            "--folds" => res.folds = Some(value.parse::<usize>().ok().and_then(|k| if k >= 2 { Some(k) } else { None })
+                .unwrap_or_else(|| panic!("Number of folds must be an integer >= 2, got `{}`", value))),
+            _ => panic!("{}", USAGE)
+        }
+    }
+
+    res
+}
+
+fn main() {
+    let args = parse_args();
+
+    let descriptors = parse_descriptors(&args.descriptors)
+        .unwrap_or_else(|e| panic!("Invalid descriptor list `{}`: {}", args.descriptors, e));
+    let algorithm = Algorithm::from_name(&args.classifier)
+        .unwrap_or_else(|| panic!("Unknown classifier `{}`", args.classifier));
+    let trainer = ModelTrainer::new(vec![StepSpec::Standardize], algorithm);
+
+    let sw = Stopwatch::start_new();
+    let samples = load_glyph_dir(&args.glyph_dir)
+        .unwrap_or_else(|e| panic!("Failed to load glyphs from `{}`: {:?}", args.glyph_dir, e));
+    println!("loaded {} glyphs in {}ms", samples.len(), sw.elapsed_ms());
+
+    let recognizer = CharRecognizer::train(&samples, descriptors, &trainer);
+    println!("alphabet: {}", recognizer.alphabet().iter().cloned().collect::<String>());
+
+    if let Some(k) = args.folds {
+        let rows: Vec<Vec<f32>> = samples.iter().map(|s| describe(recognizer.descriptors(), &s.0)).collect();
+        let labels: Vec<usize> = samples.iter()
+            .map(|s| recognizer.alphabet().binary_search(&s.1).unwrap())
+            .collect();
+
+        let evaluation = cross_validate(&trainer, &rows, &labels, k, FOLDS_SEED);
+        println!("{}-fold cross-validation accuracy: {:.4}", k, evaluation.accuracy);
+    }
+
+    recognizer.save(&args.model_file)
+        .unwrap_or_else(|e| panic!("Failed to save recognizer to `{}`: {:?}", args.model_file, e));
+}
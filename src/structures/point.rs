@@ -1,4 +1,4 @@
-#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, Copy, RustcEncodable, RustcDecodable)]
+#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Hash, Clone, Copy, RustcEncodable, RustcDecodable)]
 pub struct Point {
     pub x: i32,
     pub y: i32
@@ -1,3 +1,19 @@
-use super::Point;
+use super::{Point, Rect};
 
-pub struct Quad(Point, Point, Point, Point);
+/// Quadrilateral given by its corners clockwise from the top left one.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, RustcEncodable, RustcDecodable)]
+pub struct Quad(pub Point, pub Point, pub Point, pub Point);
+
+impl Quad {
+    pub fn from_rect(r: Rect) -> Quad {
+        Quad(r.0, Point { x: r.1.x, y: r.0.y }, r.1, Point { x: r.0.x, y: r.1.y })
+    }
+
+    /// Smallest rectangle containing all corners.
+    pub fn bounds(&self) -> Rect {
+        Rect(self.0, self.0)
+            .expand(Rect(self.1, self.1))
+            .expand(Rect(self.2, self.2))
+            .expand(Rect(self.3, self.3))
+    }
+}
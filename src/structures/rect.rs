@@ -2,7 +2,7 @@ use std::cmp;
 
 pub use super::Point;
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, RustcEncodable, RustcDecodable)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, RustcEncodable, RustcDecodable)]
 pub struct Rect(pub Point, pub Point);
 
 impl Rect {
@@ -26,6 +26,31 @@ impl Rect {
     pub fn aspect_ratio(&self) -> f32 {
         (self.width() as f32) / (self.height() as f32)
     }
+
+    pub fn area(&self) -> i32 {
+        self.width() * self.height()
+    }
+
+    pub fn intersect(&self, o: Rect) -> Option<Rect> {
+        let x_min = cmp::max(self.0.x, o.0.x);
+        let x_max = cmp::min(self.1.x, o.1.x);
+        let y_min = cmp::max(self.0.y, o.0.y);
+        let y_max = cmp::min(self.1.y, o.1.y);
+
+        if x_min <= x_max && y_min <= y_max {
+            Some(Rect(Point { x: x_min, y: y_min }, Point { x: x_max, y: y_max }))
+        } else {
+            None
+        }
+    }
+
+    /// Intersection over union.
+    pub fn iou(&self, o: Rect) -> f32 {
+        match self.intersect(o) {
+            Some(i) => (i.area() as f32) / ((self.area() + o.area() - i.area()) as f32),
+            None => 0.0f32
+        }
+    }
 }
 
 #[cfg(test)]
@@ -106,4 +131,32 @@ mod test {
         assert_eq!(r.height(), 1);
     }
 
+    #[test]
+    fn intersect_overlapping() {
+        let r1 = Rect(Point { x: 0, y: 0 }, Point { x: 3, y: 3 });
+        let r2 = Rect(Point { x: 2, y: 1 }, Point { x: 5, y: 2 });
+        let expected = Rect(Point { x: 2, y: 1 }, Point { x: 3, y: 2 });
+
+        assert_eq!(r1.intersect(r2), Some(expected));
+    }
+
+    #[test]
+    fn intersect_disjoint() {
+        let r1 = Rect(Point { x: 0, y: 0 }, Point { x: 1, y: 1 });
+        let r2 = Rect(Point { x: 2, y: 0 }, Point { x: 3, y: 1 });
+
+        assert_eq!(r1.intersect(r2), None);
+        assert_eq!(r1.iou(r2), 0.0f32);
+    }
+
+    #[test]
+    fn iou() {
+        // 4x4 and 4x2 rects sharing 2x2 area
+        let r1 = Rect(Point { x: 0, y: 0 }, Point { x: 3, y: 3 });
+        let r2 = Rect(Point { x: 2, y: 1 }, Point { x: 5, y: 2 });
+
+        assert_eq!(r1.iou(r2), 4.0f32 / 20.0f32);
+        assert_eq!(r1.iou(r1), 1.0f32);
+    }
+
 }
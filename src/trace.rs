@@ -8,29 +8,34 @@ use std::path::Path;
 use stopwatch::Stopwatch;
 
 use nprs::image;
-use nprs::extract::cser::feature::{AspectRatio, Compactness, NumHoles, HorizontalCrossings};
+use nprs::extract::cser::feature::{FeatureSet, DEFAULT_LAYOUT, parse_layout};
 use nprs::extract::cser::{FullTrace};
-use nprs::extract::cser::{Region, TracedRegion, CserDetector};
+use nprs::extract::cser::{Region, RegionParams, TracedRegion, CserDetector};
 use nprs::extract::RegionDetector;
 
-type Features = (AspectRatio, Compactness, HorizontalCrossings, NumHoles);
+type Features = FeatureSet;
 type Reg = Region<Features>;
 type Detector<'a> = CserDetector<TracedRegion<Reg>, FullTrace<'a, Reg>>;
 
 fn main() {
-    assert!(env::args().count() == 3, "usage: nprs-trace <file name> <trace file name>");
+    let args_count = env::args().count();
+    assert!(args_count == 3 || args_count == 4, "usage: nprs-trace <file name> <trace file name> [features]");
 
     if let (Some(file_name), Some(trace_file_name)) = (env::args().nth(1),  env::args().nth(2)) {
+        let features = env::args().nth(3).unwrap_or(DEFAULT_LAYOUT.to_string());
+        let layout = parse_layout(&features)
+            .unwrap_or_else(|e| panic!("Invalid feature list `{}`: {:?}", features, e));
+
         let img = image::io::load_from_file(&file_name).unwrap();
 
         let sw = Stopwatch::start_new();
         let mut full_trace: FullTrace<Reg> = FullTrace::new(
             "trace",
+            FeatureSet::schema(&layout),
             img.width(), img.height(),
             (4, 3), (150, 150)
         );
-
-        Detector::detect(&img, &mut full_trace);
+        Detector::detect(&img, &RegionParams::new(layout), &mut full_trace);
 
         println!("region detection took {}ms", sw.elapsed_ms());
 
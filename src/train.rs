@@ -1,22 +1,250 @@
-extern crate rusty_machine;
+extern crate nprs;
+extern crate stopwatch;
 
-use rusty_machine::learning::logistic_reg::LogisticRegressor;
-use rusty_machine::learning::SupModel;
-use rusty_machine::linalg::matrix::Matrix;
-use rusty_machine::linalg::vector::Vector;
+use std::env;
+use std::fs::File;
+use std::io::Write;
+
+use stopwatch::Stopwatch;
+
+use nprs::image;
+use nprs::extract::cser::feature::{FeatureSet, FeatureKind, Schema, DEFAULT_LAYOUT, parse_layout};
+use nprs::extract::cser::feature::{Feature, Frame, SecondStageKind, SecondStageSet, parse_second_stage};
+use nprs::extract::cser::{Region, RegionParams, EmptyTrace, CserDetector};
+use nprs::extract::{RegionDetector, ExtremalRegion};
+use nprs::ml::{Dataset, Sample, Model, ClassifierModel, Normalized, Trainer, ModelTrainer, Algorithm, cross_validate};
+use nprs::ml::{MiningParams, hard_negatives, subsample_background, Calibration, CalibrationKind, out_of_fold};
+use nprs::ml::normalize::StepSpec;
+use nprs::ml::ground_truth::{Annotation, load_manifest, Labeling};
+
+type Reg = Region<FeatureSet>;
+type Detector = CserDetector<Reg, EmptyTrace>;
+
+static USAGE: &'static str =
+    "usage: nprs-train <manifest file> <model file> [--features <features>] [--second-stage <features>] [--dataset <file>] [--export <file>] \
+     [--classifier logistic|adaboost|forest|mlp] [--mining-rounds <n> [--mining-min-score <score>] [--mining-max-background <n>]] \
+     [--calibration platt|isotonic] [--folds <k> [--report <json file>]]";
+
+/// Seed of the fold assignment, fixed so that reports are comparable.
+static FOLDS_SEED: u64 = 1;
+
+/// Calibration is fitted on out-of-fold scores of this many folds.
+static CALIBRATION_FOLDS: usize = 5;
+
+struct Args {
+    manifest: String,
+    model_file: String,
+    features: String,
+    second_stage: String,
+    classifier: String,
+    mining: MiningParams,
+    calibration: Option<CalibrationKind>,
+    folds: Option<usize>,
+    report: Option<String>,
+    dataset: Option<String>,
+    export: Option<String>
+}
+
+fn parse_args() -> Args {
+    let mut args = env::args().skip(1);
+    let manifest = args.next().expect(USAGE);
+    let model_file = args.next().expect(USAGE);
+    let mut res = Args {
+        manifest: manifest,
+        model_file: model_file,
+        features: DEFAULT_LAYOUT.to_string(),
+        second_stage: String::new(),
+        classifier: "logistic".to_string(),
+        mining: MiningParams::default(),
+        calibration: None,
+        folds: None,
+        report: None,
+        dataset: None,
+        export: None
+    };
+
+    while let Some(flag) = args.next() {
+        let value = args.next().expect(USAGE);
+        match &flag[..] {
+            "--features" => res.features = value,
+            "--second-stage" => res.second_stage = value,
+            "--classifier" => res.classifier = value,
+            "--folds" => res.folds = Some(value.parse::<usize>().ok().and_then(|k| if k >= 2 { Some(k) } else { None })
+                .unwrap_or_else(|| panic!("Number of folds must be an integer >= 2, got `{}`", value))),
+            "--report" => res.report = Some(value),
+            "--dataset" => res.dataset = Some(value),
+            "--export" => res.export = Some(value),
+            "--calibration" => res.calibration = Some(CalibrationKind::from_name(&value)
+                .unwrap_or_else(|| panic!("Unknown calibration `{}`, expected platt or isotonic", value))),
+            "--mining-rounds" => res.mining.rounds = value.parse()
+                .unwrap_or_else(|_| panic!("Number of mining rounds must be an integer, got `{}`", value)),
+            "--mining-min-score" => res.mining.min_score = value.parse()
+                .unwrap_or_else(|_| panic!("Mining score must be a number, got `{}`", value)),
+            "--mining-max-background" => res.mining.max_background = value.parse()
+                .unwrap_or_else(|_| panic!("Number of background samples must be an integer, got `{}`", value)),
+            _ => panic!("{}", USAGE)
+        }
+    }
+
+    res
+}
+
+/// Labeled regions detected in the annotated image, with `second_stage`
+/// features following the runtime ones. Samples don't depend on region
+/// weights, so regions aren't weighted.
+fn detect_samples(
+    a: &Annotation,
+    params: &RegionParams<Vec<FeatureKind>>,
+    second_stage: &[SecondStageKind],
+    labeling: &Labeling
+) -> Vec<Sample> {
+    let img = image::io::load_from_file(&a.image)
+        .unwrap_or_else(|e| panic!("Failed to load image `{}`: {:?}", a.image, e));
+
+    let regions = Detector::detect(&img, params, &mut EmptyTrace);
+
+    if second_stage.len() == 0 {
+        return labeling.samples(&a.image, &regions, &a.truth());
+    }
+
+    let frame = Frame::new(&img);
+    labeling.samples_with(&a.image, &regions, &a.truth(), |r, out| {
+        SecondStageSet::compute(second_stage, r.points(), &frame).value(out)
+    })
+}
+
+fn to_model(features: &str, second_stage: &str, schema: &Schema, trained: Normalized<ClassifierModel>) -> Model {
+    Model {
+        features: features.to_string(),
+        second_stage: second_stage.to_string(),
+        schema: schema.clone(),
+        classifier: trained,
+        calibration: Calibration::Identity
+    }
+}
 
 fn main() {
-    let inputs = Matrix::new(4,1,vec![1.0,3.0,5.0,7.0]);
-    let targets = Vector::new(vec![0.,0.,1.,1.]);
+    let args = parse_args();
+    let (manifest, model_file, features) = (args.manifest, args.model_file, args.features);
+
+    let algorithm = Algorithm::from_name(&args.classifier)
+        .unwrap_or_else(|| panic!("Unknown classifier `{}`, expected logistic, adaboost, forest or mlp", args.classifier));
+    let trainer = ModelTrainer::new(vec![StepSpec::Standardize], algorithm);
+
+    let layout = parse_layout(&features)
+        .unwrap_or_else(|e| panic!("Invalid feature list `{}`: {:?}", features, e));
+
+    // manifest images are only needed to detect regions
+    let annotations = if args.dataset.is_none() || args.mining.rounds > 0 {
+        load_manifest(&manifest)
+            .unwrap_or_else(|e| panic!("Failed to load manifest `{}`: {:?}", manifest, e))
+    } else {
+        vec![]
+    };
+
+    let second_stage = parse_second_stage(&args.second_stage)
+        .unwrap_or_else(|e| panic!("Invalid second-stage feature list `{}`: {:?}", args.second_stage, e));
+
+    let labeling = Labeling::default();
+    let mut schema = FeatureSet::schema(&layout);
+    schema.columns.extend(SecondStageSet::schema(&second_stage).columns);
+    let params = RegionParams::new(layout);
+
+    let mut dataset = match args.dataset {
+        Some(ref path) => {
+            let loaded = Dataset::load(path)
+                .unwrap_or_else(|e| panic!("Failed to load dataset `{}`: {:?}", path, e));
+            schema.check(&loaded.schema)
+                .unwrap_or_else(|e| panic!("Dataset `{}` doesn't match features `{}`: {:?}", path, features, e));
+
+            println!("loaded {} samples ({} characters) from `{}`", loaded.len(), loaded.num_characters(), path);
+            // CSV doesn't keep column ranges
+            Dataset { schema: schema.clone(), samples: loaded.samples }
+        },
+        None => {
+            let mut dataset = Dataset::new(schema.clone());
+            let sw = Stopwatch::start_new();
+            for a in annotations.iter() {
+                let samples = detect_samples(a, &params, &second_stage, &labeling);
+                // background left out is what mining rounds pick from
+                let samples = if args.mining.rounds > 0 {
+                    subsample_background(samples, &args.mining)
+                } else {
+                    samples
+                };
+                for s in samples {
+                    dataset.push(s);
+                }
+            }
+
+            println!(
+                "collected {} samples ({} characters) from {} images in {}ms",
+                dataset.len(), dataset.num_characters(), annotations.len(), sw.elapsed_ms()
+            );
+            dataset
+        }
+    };
+
+    let mut model = to_model(&features, &args.second_stage, &dataset.schema, trainer.train(&dataset.rows(), &dataset.labels()));
+
+    // regions that the current model confuses with characters are
+    // retrained as negatives
+    for round in 0..args.mining.rounds {
+        let sw = Stopwatch::start_new();
+        let known = dataset.region_keys();
+
+        let mut mined = vec![];
+        for a in annotations.iter() {
+            let candidates = detect_samples(a, &params, &second_stage, &labeling);
+            mined.extend(hard_negatives(&model, candidates, &known, &args.mining));
+        }
+
+        println!("mining round {}: {} hard negatives in {}ms", round + 1, mined.len(), sw.elapsed_ms());
+
+        if mined.len() == 0 {
+            break;
+        }
+
+        for s in mined {
+            dataset.push(s);
+        }
+        model = to_model(&features, &args.second_stage, &dataset.schema, trainer.train(&dataset.rows(), &dataset.labels()));
+    }
+
+    if let Some(ref path) = args.export {
+        dataset.save(path)
+            .unwrap_or_else(|e| panic!("Failed to export dataset to `{}`: {:?}", path, e));
+    }
+
+    if let Some(k) = args.folds {
+        let evaluation = cross_validate(&trainer, &dataset.rows(), &dataset.labels(), k, FOLDS_SEED);
+        println!("{}-fold cross-validation:\n{}", k, evaluation.to_text());
 
-    let mut log_mod = LogisticRegressor::default();
+        if let Some(ref report) = args.report {
+            let json = evaluation.to_json()
+                .unwrap_or_else(|e| panic!("Failed to encode evaluation report: {:?}", e));
+            File::create(report)
+                .and_then(|mut f| f.write_all(json.as_bytes()))
+                .unwrap_or_else(|e| panic!("Failed to write evaluation report to `{}`: {:?}", report, e));
+        }
+    }
 
-    // Train the model
-    log_mod.train(&inputs, &targets);
+    // scores of samples the model was trained on are overconfident, so
+    // the mapping is fitted on scores of models trained on other folds
+    if let Some(kind) = args.calibration {
+        let labels = dataset.labels();
+        let oof = out_of_fold(&trainer, &dataset.rows(), &labels, CALIBRATION_FOLDS, FOLDS_SEED);
+        model.calibration = Calibration::fit(kind, &oof.scores, &labels);
+        println!("calibration: {:?}", model.calibration);
+    }
 
-    // Now we'll predict a new point
-    let new_point = Matrix::new(1,1,vec![110.]);
-    let output = log_mod.predict(&new_point);
+    if let ClassifierModel::RandomForest(ref forest) = model.classifier.classifier {
+        println!("feature importance:");
+        for (name, importance) in model.schema.names().iter().zip(forest.feature_importance().iter()) {
+            println!("  {:<32} {:.4}", name, importance);
+        }
+    }
 
-    println!("{:?}", &output);
+    model.save(&model_file)
+        .unwrap_or_else(|e| panic!("Failed to save model to `{}`: {:?}", model_file, e));
 }